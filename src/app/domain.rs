@@ -1,7 +1,33 @@
+use shared::serialization::Builtin;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+const PARAM_PLACEHOLDER: &str = "{}";
+
 pub struct SelectableItemModel {
     pub index: usize,
     pub label: String,
+    /// The label in lowercase, what the filter matches against.
+    pub filter_label: String,
     pub param: String,
+    pub launch_param: OsString,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub tags: Vec<String>,
+    pub keywords: Vec<String>,
+    pub command_template: Option<String>,
+    pub is_terminal: Option<bool>,
+    pub cwd: Option<String>,
+    pub env: BTreeMap<String, String>,
+    pub values: BTreeMap<String, String>,
+}
+
+impl SelectableItemModel {
+    pub fn is_match(&self, input: &str) -> bool {
+        self.filter_label.contains(input)
+            || self.tags.iter().any(|x| x.contains(input))
+            || self.keywords.iter().any(|x| x.contains(input))
+    }
 }
 
 pub struct GroupModel {
@@ -9,12 +35,26 @@ pub struct GroupModel {
     pub items: Vec<SelectableItemModel>,
     pub command_template: Option<String>,
     pub is_terminal: Option<bool>,
+    pub actions: Vec<ActionModel>,
+}
+
+pub struct ActionModel {
+    pub name: String,
+    pub command_template: Option<String>,
+    pub is_terminal: Option<bool>,
+    pub builtin: Option<Builtin>,
 }
 
 pub struct LaunchModel {
     pub executable: Option<String>,
     pub param: Option<String>,
+    /// The exact value passed to the command, `param` is a lossy copy for display.
+    pub launch_param: Option<OsString>,
     pub is_terminal: bool,
+    pub cwd: Option<String>,
+    pub env: BTreeMap<String, String>,
+    pub values: BTreeMap<String, String>,
+    pub clipboard: Option<String>,
 }
 
 impl LaunchModel {
@@ -22,7 +62,151 @@ impl LaunchModel {
         LaunchModel {
             executable: None,
             param: None,
+            launch_param: None,
             is_terminal: false,
+            cwd: None,
+            env: BTreeMap::new(),
+            values: BTreeMap::new(),
+            clipboard: None,
+        }
+    }
+
+    /// The program and its arguments: the template split on whitespace with `{}` replaced by
+    /// the param and `{name}` by the item's values. The param is appended if there is no `{}`.
+    pub fn command_line(&self) -> Vec<OsString> {
+        let executable = match &self.executable {
+            Some(executable) => executable,
+            None => return vec![],
+        };
+
+        let param = self
+            .launch_param
+            .clone()
+            .or_else(|| self.param.as_ref().map(OsString::from));
+
+        let mut has_placeholder = false;
+        let mut command = vec![];
+
+        for part in executable.split_whitespace() {
+            if part == PARAM_PLACEHOLDER {
+                has_placeholder = true;
+                command.extend(param.clone());
+                continue;
+            }
+
+            let mut part = part.to_string();
+
+            if part.contains(PARAM_PLACEHOLDER) {
+                has_placeholder = true;
+                let lossy = self.param.as_deref().unwrap_or_default();
+                part = part.replace(PARAM_PLACEHOLDER, lossy);
+            }
+
+            for (name, value) in self.values.iter() {
+                part = part.replace(&format!("{{{}}}", name), value);
+            }
+
+            command.push(OsString::from(part));
+        }
+
+        if !has_placeholder {
+            command.extend(param);
+        }
+
+        command
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        if let Some(text) = &self.clipboard {
+            return vec![format!("copy: {}", text)];
+        }
+
+        if self.executable.is_none() {
+            return vec![String::from("nothing to launch")];
+        }
+
+        let command_line: Vec<String> = self
+            .command_line()
+            .iter()
+            .map(|x| quote(&x.to_string_lossy()))
+            .collect();
+
+        let mut lines = vec![format!("command: {}", command_line.join(" "))];
+        lines.push(format!(
+            "cwd: {}",
+            self.cwd.as_deref().unwrap_or("(inherited)")
+        ));
+
+        for (key, value) in self.env.iter() {
+            lines.push(format!("env: {}={}", key, value));
+        }
+
+        if self.is_terminal {
+            lines.push(String::from("terminal: true"));
+        } else if cfg!(target_family = "windows") {
+            lines.push(String::from("terminal: false, spawned directly"));
+        } else {
+            lines.push(String::from("terminal: false, detached with launcher"));
+        }
+
+        lines
+    }
+
+    pub fn copy(text: &str) -> LaunchModel {
+        LaunchModel {
+            clipboard: Some(String::from(text)),
+            ..LaunchModel::default()
+        }
+    }
+}
+
+fn quote(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| !c.is_whitespace() && !"'\"\\$`".contains(c));
+
+    if is_plain {
+        return String::from(arg);
+    }
+
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn launch(template: &str, param: &str) -> LaunchModel {
+        LaunchModel {
+            executable: Some(String::from(template)),
+            param: Some(String::from(param)),
+            launch_param: Some(OsString::from(param)),
+            values: BTreeMap::from([(String::from("line"), String::from("42"))]),
+            ..LaunchModel::default()
         }
     }
+
+    #[test]
+    fn appends_the_param_without_a_placeholder() {
+        let command = launch("code --wait", "my file.rs").command_line();
+
+        assert_eq!(command, vec!["code", "--wait", "my file.rs"]);
+    }
+
+    #[test]
+    fn fills_the_placeholders_of_the_template() {
+        let command = launch("vim +{line} {}", "my file.rs").command_line();
+        assert_eq!(command, vec!["vim", "+42", "my file.rs"]);
+
+        let command = launch("code --goto {}:{line}", "main.rs").command_line();
+        assert_eq!(command, vec!["code", "--goto", "main.rs:42"]);
+    }
+
+    #[test]
+    fn describes_the_filled_template() {
+        let lines = launch("vim +{line} {}", "my file.rs").describe();
+
+        assert_eq!(lines[0], "command: vim +42 'my file.rs'");
+    }
 }
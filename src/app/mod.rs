@@ -1,4 +1,5 @@
 pub mod domain;
 pub mod model;
+pub mod opener;
 pub mod state;
 pub mod stateful;
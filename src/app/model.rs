@@ -1,76 +1,214 @@
-use super::domain::{GroupModel, SelectableItemModel};
+use super::domain::{ActionModel, GroupModel, SelectableItemModel};
 use crate::app::domain::LaunchModel;
+use crate::app::opener::Opener;
 use crate::State;
-use serde::Deserialize;
-use shared::serialization::Groups;
-use std::env;
+use shared::serialization::{Builtin, Format, Groups, ListGroup, SelectableItem};
 
 pub struct AppModel {
     pub groups: Vec<GroupModel>,
+    sources: Vec<usize>,
+    opener: Opener,
 }
 
 impl<'a> AppModel {
-    pub fn new<R>(reader: R) -> AppModel
+    pub fn new<R>(reader: R, format: Option<Format>, opener: Opener) -> AppModel
     where
         R: std::io::Read,
     {
-        let mut de = serde_json::Deserializer::from_reader(reader);
-        let data = Groups::deserialize(&mut de);
+        let data = Groups::from_reader(reader, format);
 
         match data {
             Ok(content) => {
-                let model_groups = content
-                    .groups
-                    .iter()
-                    .map(|group| GroupModel {
-                        label: group.label.clone(),
-                        command_template: group.command_template.clone(),
-                        is_terminal: group.is_terminal,
-                        items: group
-                            .items
-                            .iter()
-                            .enumerate()
-                            .map(|(index, x)| SelectableItemModel {
-                                label: x.label.to_lowercase(),
-                                param: x.param.clone(),
-                                index: index,
-                            })
-                            .collect(),
-                    })
-                    .collect();
-
                 let model = AppModel {
-                    groups: model_groups,
+                    groups: AppModel::map_groups(&content),
+                    sources: vec![0; content.groups.len()],
+                    opener,
                 };
 
                 return model;
             }
             Err(e) => {
-                panic!("can't read json state: {}", e.to_string());
+                panic!("can't read state: {}", e);
             }
         };
     }
 
-    pub fn handle_enter(&self, state: &State) -> LaunchModel {
+    pub fn empty(opener: Opener) -> AppModel {
+        AppModel {
+            groups: vec![],
+            sources: vec![],
+            opener,
+        }
+    }
+
+    /// Adds the groups of a plugin after the ones of the plugins before it. A plugin streaming
+    /// its output sends a group in several batches, a group with the label of one it sent before
+    /// adds its items to that one. Returns the position and count of the new groups.
+    pub fn insert_groups(&mut self, source: usize, groups: &Groups) -> (usize, usize) {
+        let start = self.sources.iter().take_while(|x| **x < source).count();
+        let position = self.sources.iter().take_while(|x| **x <= source).count();
+        let mut models = vec![];
+
+        for group in groups.groups.iter() {
+            let existing = self.groups[start..position]
+                .iter_mut()
+                .find(|x| x.label == group.label);
+
+            match existing {
+                Some(existing) => {
+                    let offset = existing.items.len();
+                    let items = AppModel::map_items(&group.items, offset);
+                    existing.items.extend(items);
+                }
+                None => models.push(AppModel::map_group(group)),
+            }
+        }
+
+        let count = models.len();
+
+        self.groups.splice(position..position, models);
+        self.sources.splice(position..position, vec![source; count]);
+
+        (position, count)
+    }
+
+    fn map_groups(groups: &Groups) -> Vec<GroupModel> {
+        groups.groups.iter().map(AppModel::map_group).collect()
+    }
+
+    fn map_group(group: &ListGroup) -> GroupModel {
+        GroupModel {
+            label: group.label.clone(),
+            command_template: group.command_template.clone(),
+            is_terminal: group.is_terminal,
+            actions: group
+                .actions
+                .iter()
+                .map(|x| ActionModel {
+                    name: x.name.clone(),
+                    command_template: x.command_template.clone(),
+                    is_terminal: x.is_terminal,
+                    builtin: x.builtin,
+                })
+                .collect(),
+            items: AppModel::map_items(&group.items, 0),
+        }
+    }
+
+    // `offset` is the number of items the group already has, the index points into all of them
+    fn map_items(items: &[SelectableItem], offset: usize) -> Vec<SelectableItemModel> {
+        items
+            .iter()
+            .enumerate()
+            .map(|(index, x)| SelectableItemModel {
+                label: x.label.clone(),
+                filter_label: x.label.to_lowercase(),
+                param: x.param.clone(),
+                launch_param: x.launch_param(),
+                index: offset + index,
+                description: x.description.clone(),
+                icon: x.icon.clone(),
+                tags: x.tags.iter().map(|tag| tag.to_lowercase()).collect(),
+                keywords: x.keywords.iter().map(|kw| kw.to_lowercase()).collect(),
+                command_template: x.command_template.clone(),
+                is_terminal: x.is_terminal,
+                cwd: x.cwd.clone(),
+                env: x.env.clone(),
+                values: x.values.clone(),
+            })
+            .collect()
+    }
+
+    /// Items without a param, like the error of a failed plugin, only show a message.
+    /// Nothing is selected in an empty group or once the filter matches no row.
+    pub fn is_launchable(&self, state: &State) -> bool {
+        self.get_selected_item(state)
+            .is_some_and(|x| !x.param.is_empty())
+    }
+
+    pub fn handle_enter(&self, state: &State) -> Option<LaunchModel> {
+        let group = &self.groups[state.get_selected_group()];
+        let item = self.get_selected_item(state)?;
+
+        if item.param.is_empty() {
+            return Some(LaunchModel::default());
+        }
+
+        let command_template = item
+            .command_template
+            .as_ref()
+            .or(group.command_template.as_ref())
+            .cloned()
+            .unwrap_or_else(|| self.opener.resolve(&item.param));
+
+        let is_terminal = item.is_terminal.or(group.is_terminal);
+
+        Some(AppModel::create_launch(
+            item,
+            Some(command_template),
+            is_terminal,
+        ))
+    }
+
+    pub fn handle_action(&self, state: &State, action_index: usize) -> Option<LaunchModel> {
+        let group = &self.groups[state.get_selected_group()];
+        let item = self.get_selected_item(state)?;
+        let action = &group.actions[action_index];
+
+        if let Some(builtin) = action.builtin {
+            return Some(AppModel::handle_builtin(item, builtin));
+        }
+
+        let command_template = action
+            .command_template
+            .clone()
+            .unwrap_or_else(|| self.opener.resolve(&item.param));
+
+        let is_terminal = action.is_terminal.or(group.is_terminal);
+
+        Some(AppModel::create_launch(
+            item,
+            Some(command_template),
+            is_terminal,
+        ))
+    }
+
+    pub fn handle_copy(&self, state: &State, builtin: Builtin) -> Option<LaunchModel> {
+        let item = self.get_selected_item(state)?;
+        Some(AppModel::handle_builtin(item, builtin))
+    }
+
+    fn handle_builtin(item: &SelectableItemModel, builtin: Builtin) -> LaunchModel {
+        match builtin {
+            Builtin::Copy => LaunchModel::copy(&item.param),
+            Builtin::CopyLabel => LaunchModel::copy(&item.label),
+        }
+    }
+
+    fn get_selected_item(&self, state: &State) -> Option<&SelectableItemModel> {
         let selected_group_index = state.get_selected_group();
         let selected_list = &state.lists[selected_group_index];
         let selected_item_index = selected_list.get_selected();
 
-        let global_index = state.get_by_local_index(selected_item_index);
+        let global_index = state.get_by_local_index(selected_item_index)?;
 
-        let group = &self.groups[selected_group_index];
-        let selected_item_model = &group.items[global_index];
-
-        let mut cwd = env::current_exe().unwrap();
-        cwd.pop();
-        cwd.push("launcher");
-
-        let launch = LaunchModel {
-            executable: group.command_template.clone(),
-            param: Some(selected_item_model.param.clone()),
-            is_terminal: group.is_terminal.unwrap_or(false),
-        };
+        self.groups[selected_group_index].items.get(global_index)
+    }
 
-        launch
+    fn create_launch(
+        item: &SelectableItemModel,
+        command_template: Option<String>,
+        is_terminal: Option<bool>,
+    ) -> LaunchModel {
+        LaunchModel {
+            executable: command_template,
+            param: Some(item.param.clone()),
+            launch_param: Some(item.launch_param.clone()),
+            is_terminal: is_terminal.unwrap_or(false),
+            cwd: item.cwd.clone(),
+            env: item.env.clone(),
+            values: item.values.clone(),
+            clipboard: None,
+        }
     }
 }
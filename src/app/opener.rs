@@ -0,0 +1,130 @@
+use crate::config::OpenerConfig;
+use std::env;
+use std::path::Path;
+
+pub struct Opener {
+    config: OpenerConfig,
+}
+
+impl Opener {
+    pub fn new(config: OpenerConfig) -> Opener {
+        Opener { config }
+    }
+
+    pub fn resolve(&self, param: &str) -> String {
+        if is_url(param) {
+            return self.browser();
+        }
+
+        let path = Path::new(param);
+
+        if path.is_dir() {
+            return self.file_manager();
+        }
+
+        self.by_type(path).unwrap_or_else(system_opener)
+    }
+
+    fn browser(&self) -> String {
+        self.config
+            .browser
+            .clone()
+            .or_else(|| env::var("BROWSER").ok())
+            .unwrap_or_else(system_opener)
+    }
+
+    fn file_manager(&self) -> String {
+        self.config
+            .file_manager
+            .clone()
+            .or_else(|| env::var("FILE_MANAGER").ok())
+            .unwrap_or_else(system_opener)
+    }
+
+    fn by_type(&self, path: &Path) -> Option<String> {
+        let types = &self.config.types;
+
+        if let Some(extension) = path.extension().and_then(|x| x.to_str()) {
+            if let Some(command) = types.get(&extension.to_lowercase()) {
+                return Some(command.clone());
+            }
+        }
+
+        let mime = mime_guess::from_path(path).first()?;
+
+        types
+            .get(mime.essence_str())
+            .or_else(|| types.get(&format!("{}/*", mime.type_())))
+            .cloned()
+    }
+}
+
+fn is_url(param: &str) -> bool {
+    param.contains("://") || param.starts_with("mailto:")
+}
+
+fn system_opener() -> String {
+    if cfg!(target_os = "macos") {
+        String::from("open")
+    } else if cfg!(target_family = "windows") {
+        String::from("explorer")
+    } else {
+        String::from("xdg-open")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn opener(types: &[(&str, &str)]) -> Opener {
+        Opener::new(OpenerConfig {
+            types: types
+                .iter()
+                .map(|(key, command)| (key.to_string(), command.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+            ..OpenerConfig::default()
+        })
+    }
+
+    // the only tests reading these variables, the values don't leak into others
+    #[test]
+    fn opens_urls_in_the_browser() {
+        env::set_var("BROWSER", "test-browser");
+
+        assert_eq!(opener(&[]).resolve("https://example.com"), "test-browser");
+        assert_eq!(opener(&[]).resolve("mailto:me@example.com"), "test-browser");
+
+        let configured = Opener::new(OpenerConfig {
+            browser: Some(String::from("firefox")),
+            ..OpenerConfig::default()
+        });
+        assert_eq!(configured.resolve("https://example.com"), "firefox");
+    }
+
+    #[test]
+    fn opens_directories_in_the_file_manager() {
+        env::set_var("FILE_MANAGER", "test-files");
+        let dir = env::temp_dir();
+
+        assert_eq!(opener(&[]).resolve(dir.to_str().unwrap()), "test-files");
+    }
+
+    #[test]
+    fn looks_files_up_by_extension_then_mime_type() {
+        let opener = opener(&[("md", "typora"), ("image/*", "feh"), ("text/x-toml", "vim")]);
+
+        assert_eq!(opener.resolve("notes.MD"), "typora");
+        assert_eq!(opener.resolve("photo.png"), "feh");
+        assert_eq!(opener.resolve("Cargo.toml"), "vim");
+    }
+
+    #[test]
+    fn falls_back_to_the_system_opener() {
+        let opener = opener(&[("md", "typora")]);
+
+        assert_eq!(opener.resolve("archive.unknown-type"), system_opener());
+        assert_eq!(opener.resolve("Makefile"), system_opener());
+    }
+}
@@ -5,6 +5,10 @@ pub struct State {
     pub lists: Vec<StatefulList>,
     pub groups: StatefulList,
     index_map: HashMap<usize, usize>,
+    pub action_menu: Option<StatefulList>,
+    pub explain: Option<Vec<String>>,
+    /// Plugins are still running, more groups may arrive.
+    pub is_loading: bool,
     input: String,
     input_changed: bool,
 }
@@ -15,11 +19,35 @@ impl State {
             lists: items.iter().map(|x| StatefulList::from(&x.items)).collect(),
             groups: StatefulList::from(&items),
             index_map: HashMap::new(),
+            action_menu: None,
+            explain: None,
+            is_loading: false,
             input: String::new(),
             input_changed: false,
         }
     }
 
+    pub fn insert_groups(&mut self, position: usize, items: &[GroupModel]) {
+        let lists = items.iter().map(|x| StatefulList::from(&x.items));
+        self.lists.splice(position..position, lists);
+
+        let selected = self.groups.get_selected();
+        let has_shifted = self.groups.len > 0 && selected >= position;
+
+        self.groups.len += items.len();
+
+        if has_shifted {
+            self.groups.state.select(Some(selected + items.len()));
+        }
+    }
+
+    /// Catches up with groups that got more items, from a plugin streaming its output.
+    pub fn update_lengths(&mut self, items: &[GroupModel]) {
+        for (list, group) in self.lists.iter_mut().zip(items) {
+            list.len = group.items.len();
+        }
+    }
+
     pub fn select_item_next(&mut self) {
         let selected_group = self.groups.get_selected();
         self.lists[selected_group].next();
@@ -66,11 +94,28 @@ impl State {
         self.index_map.insert(index_local, index_global);
     }
 
-    pub fn get_by_local_index(&self, index_local: usize) -> usize {
-        self.index_map[&index_local]
+    /// The index within the group of a row of the filtered list, `None` if there is no such row.
+    pub fn get_by_local_index(&self, index_local: usize) -> Option<usize> {
+        self.index_map.get(&index_local).copied()
     }
 
     pub fn is_empty(&self) -> bool {
         self.input.len() == 0
     }
+
+    pub fn open_action_menu<T>(&mut self, actions: &Vec<T>) {
+        if actions.is_empty() {
+            return;
+        }
+
+        self.action_menu = Some(StatefulList::from(actions));
+    }
+
+    pub fn close_action_menu(&mut self) {
+        self.action_menu = None;
+    }
+
+    pub fn is_action_menu_open(&self) -> bool {
+        self.action_menu.is_some()
+    }
 }
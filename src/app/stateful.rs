@@ -18,6 +18,10 @@ impl StatefulList {
     }
 
     pub fn next(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+
         let i = match self.state.selected() {
             Some(i) => {
                 if i == self.len - 1 {
@@ -32,6 +36,10 @@ impl StatefulList {
     }
 
     pub fn previous(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -0,0 +1,34 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+pub fn osc52(text: &str) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+
+    // tmux swallows unknown sequences unless they are wrapped into its passthrough
+    if env::var_os("TMUX").is_some() {
+        return format!("\x1bPtmux;\x1b{}\x1b\\", sequence);
+    }
+
+    sequence
+}
+
+pub fn copy_with(command: &str, text: &str) -> io::Result<()> {
+    let mut parts = command.split_whitespace();
+
+    let first = parts
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty clipboard command"))?;
+
+    let mut child = Command::new(first)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    child.stdin.take().unwrap().write_all(text.as_bytes())?;
+    child.wait()?;
+
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use shared::discovery;
+use shared::plugin::{PluginDescription, DESCRIBE_FLAG};
+use shared::serialization::{Format, Groups};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub enum Subcommand {
+    #[structopt(about = "Prints the JSON schema of the document passed between plugins")]
+    Schema,
+    #[structopt(
+        about = "Checks that a document reads as the current protocol version, reads stdin if no file is given",
+        long_about = "Checks that a document reads as the current protocol version, reads stdin if no file is given. \
+                      The document is deserialized, not matched against the printed schema, \
+                      so the errors are the ones justlist itself would report."
+    )]
+    Validate {
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+        #[structopt(long, short, help = "json, yaml or toml")]
+        format: Option<Format>,
+    },
+    #[structopt(about = "Lists installed plugins or describes one of them")]
+    Plugins(PluginsCommand),
+}
+
+#[derive(Debug, StructOpt)]
+pub enum PluginsCommand {
+    #[structopt(
+        about = "Lists bundled plugins, the plugins directory and justlist-<name> on PATH"
+    )]
+    List,
+    #[structopt(about = "Prints the options a plugin accepts")]
+    Describe { name: String },
+}
+
+pub fn execute(command: Subcommand) -> io::Result<()> {
+    match command {
+        Subcommand::Schema => print_schema(),
+        Subcommand::Validate { file, format } => validate(file, format),
+        Subcommand::Plugins(PluginsCommand::List) => list_plugins(),
+        Subcommand::Plugins(PluginsCommand::Describe { name }) => describe_plugin(&name),
+    }
+}
+
+fn print_schema() -> io::Result<()> {
+    let schema = Groups::schema();
+    let stdout = io::stdout();
+    serde_json::to_writer_pretty(stdout.lock(), &schema)?;
+    println!();
+
+    Ok(())
+}
+
+fn validate(file: Option<PathBuf>, format: Option<Format>) -> io::Result<()> {
+    let result = match file {
+        Some(file_path) => {
+            let format = format.or_else(|| Format::from_path(&file_path));
+            let reader = BufReader::new(File::open(file_path)?);
+            check(reader, format)
+        }
+        None => {
+            let stdin = io::stdin();
+            check(stdin.lock(), format)
+        }
+    };
+
+    match result {
+        Ok(summary) => println!("{}", summary),
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn check(reader: impl Read, format: Option<Format>) -> io::Result<String> {
+    let groups = Groups::from_reader(reader, format)?;
+    let items: usize = groups.groups.iter().map(|x| x.items.len()).sum();
+
+    Ok(format!(
+        "ok: {} groups, {} items",
+        groups.groups.len(),
+        items
+    ))
+}
+
+fn list_plugins() -> io::Result<()> {
+    for plugin in discovery::discover() {
+        let description = plugin
+            .describe()
+            .and_then(|x| x.description)
+            .unwrap_or_else(|| String::from("(no description)"));
+
+        println!(
+            "{}\t{}\t{}",
+            plugin.name,
+            plugin.path.display(),
+            description
+        );
+    }
+
+    Ok(())
+}
+
+fn describe_plugin(name: &str) -> io::Result<()> {
+    let plugin = discovery::find(name).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("unknown plugin: {}", name))
+    })?;
+
+    let description = plugin
+        .describe()
+        .ok_or_else(|| io::Error::other(format!("{} does not support {}", name, DESCRIBE_FLAG)))?;
+
+    print_description(&plugin.path, &description);
+
+    Ok(())
+}
+
+fn print_description(path: &Path, description: &PluginDescription) {
+    println!("{} ({})", description.name, path.display());
+
+    if let Some(about) = &description.description {
+        println!("{}", about);
+    }
+
+    println!();
+
+    for option in description.options.iter() {
+        let mut flags = vec![];
+
+        if let Some(short) = option.short {
+            flags.push(format!("-{}", short));
+        }
+
+        if let Some(long) = &option.long {
+            flags.push(format!("--{}", long));
+        }
+
+        let mut usage = if flags.is_empty() {
+            format!("<{}>", option.name)
+        } else {
+            flags.join(", ")
+        };
+
+        if !flags.is_empty() && option.takes_value {
+            usage.push_str(&format!(" <{}>", option.name));
+        }
+
+        let required = if option.required { " (required)" } else { "" };
+        let help = option.help.as_deref().unwrap_or("");
+
+        let line = format!("    {:<30}{}{}", usage, help, required);
+        println!("{}", line.trim_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::serialization::PROTOCOL_VERSION;
+
+    fn error_of(content: &str, format: Option<Format>) -> String {
+        check(content.as_bytes(), format).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn summarizes_a_valid_document() {
+        let content = r#"{"groups": [{"label": "a", "items": [{"label": "x", "param": "y"}]}]}"#;
+
+        assert_eq!(
+            check(content.as_bytes(), None).unwrap(),
+            "ok: 1 groups, 1 items"
+        );
+    }
+
+    #[test]
+    fn documents_without_a_version_are_read_as_the_first_one() {
+        let groups = Groups::parse(r#"{"groups": []}"#, None).unwrap();
+
+        assert_eq!(groups.version, 1);
+        assert_eq!(Groups::new().version, PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn reports_the_path_of_the_offending_field() {
+        let json = r#"{"groups": [{"label": "a", "items": [{"label": "x"}]}]}"#;
+        let error = error_of(json, None);
+        assert!(
+            error.starts_with("groups[0].items[0]: missing field `param`"),
+            "{}",
+            error
+        );
+
+        let yaml = "groups:\n  - label: a\n    items:\n      - label: x\n        param: [1]\n";
+        let error = error_of(yaml, Some(Format::Yaml));
+        assert!(
+            error.starts_with("groups[0].items[0].param: invalid type"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_newer_protocol_versions() {
+        let error = error_of(r#"{"version": 99, "groups": []}"#, None);
+
+        assert!(
+            error.starts_with("version: unsupported protocol version 99"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use serde::Deserialize;
+use shared::paths;
+use shared::runner::PluginCommand;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE: &str = "justlist.toml";
+
+#[derive(Deserialize, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub opener: OpenerConfig,
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Profile {
+    pub plugins: Vec<PluginCommand>,
+    /// Seconds to wait for each plugin unless it sets its own timeout.
+    pub timeout: Option<u64>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct OpenerConfig {
+    pub browser: Option<String>,
+    pub file_manager: Option<String>,
+    #[serde(default)]
+    pub types: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct ClipboardConfig {
+    pub command: Option<String>,
+}
+
+impl Config {
+    pub fn default_path() -> PathBuf {
+        paths::config_dir().join(CONFIG_FILE)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Config> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(error) => return Err(error),
+        };
+
+        toml::from_str(&content).map_err(|e| {
+            let message = format!("{}: {}", path.display(), e);
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        })
+    }
+}
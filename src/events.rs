@@ -0,0 +1,291 @@
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Where `run_app` takes its input from. The snapshot tests drive `run_app` with a scripted
+/// `ReplayEvents` and a `TestBackend`, without a terminal to read keys from, which is why the
+/// app doesn't call crossterm directly and the terminal is just one source, `TerminalEvents`.
+pub trait EventSource {
+    /// Waits up to `timeout` for the next event, `None` if nothing arrived in time.
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+}
+
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if !event::poll(timeout)? {
+            return Ok(None);
+        }
+
+        event::read().map(Some)
+    }
+}
+
+/// Plays back keys from a script, one key per line as written by `--record`.
+pub struct ReplayEvents {
+    events: VecDeque<Event>,
+    then: Option<TerminalEvents>,
+}
+
+impl ReplayEvents {
+    pub fn new(keys: Vec<KeyEvent>) -> ReplayEvents {
+        ReplayEvents {
+            events: keys.into_iter().map(Event::Key).collect(),
+            then: None,
+        }
+    }
+
+    pub fn from_file(path: &Path) -> io::Result<ReplayEvents> {
+        let content = fs::read_to_string(path)?;
+        let mut keys = vec![];
+
+        for (number, line) in content.lines().enumerate() {
+            let parsed = parse_line(line).map_err(|e| {
+                let message = format!("{}:{}: {}", path.display(), number + 1, e);
+                io::Error::new(io::ErrorKind::InvalidData, message)
+            })?;
+
+            keys.extend(parsed);
+        }
+
+        Ok(ReplayEvents::new(keys))
+    }
+
+    /// Hands over to the terminal once the script is over, instead of failing.
+    pub fn then_terminal(mut self) -> Self {
+        self.then = Some(TerminalEvents);
+        self
+    }
+}
+
+impl EventSource for ReplayEvents {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Some(event));
+        }
+
+        match self.then.as_mut() {
+            Some(terminal) => terminal.next_event(timeout),
+            // nothing can end the session anymore, so don't keep waiting
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the replay ended before justlist exited",
+            )),
+        }
+    }
+}
+
+/// Writes every key read from the wrapped source to a file, in the format read by `--replay`.
+pub struct RecordEvents<S> {
+    source: S,
+    file: File,
+}
+
+impl<S: EventSource> RecordEvents<S> {
+    pub fn new(source: S, path: &Path) -> io::Result<RecordEvents<S>> {
+        Ok(RecordEvents {
+            source,
+            file: File::create(path)?,
+        })
+    }
+}
+
+impl<S: EventSource> EventSource for RecordEvents<S> {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        let event = self.source.next_event(timeout)?;
+
+        if let Some(Event::Key(key)) = &event {
+            if let Some(line) = format_key(key) {
+                writeln!(self.file, "{}", line)?;
+                self.file.flush()?;
+            }
+        }
+
+        Ok(event)
+    }
+}
+
+impl<S: EventSource + ?Sized> EventSource for Box<S> {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        (**self).next_event(timeout)
+    }
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 13] = [
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Backspace", KeyCode::Backspace),
+    ("Tab", KeyCode::Tab),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Space", KeyCode::Char(' ')),
+];
+
+const MODIFIERS: [(&str, KeyModifiers); 3] = [
+    ("Ctrl-", KeyModifiers::CONTROL),
+    ("Alt-", KeyModifiers::ALT),
+    ("Shift-", KeyModifiers::SHIFT),
+];
+
+const TEXT_PREFIX: &str = "text ";
+
+// a line is a key like `a`, `Enter` or `Ctrl-O`, or `text <chars>` to type several at once
+fn parse_line(line: &str) -> Result<Vec<KeyEvent>, String> {
+    let line = line.trim_end_matches(['\r', '\n']);
+
+    if line.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    if let Some(text) = line.strip_prefix(TEXT_PREFIX) {
+        return Ok(text
+            .chars()
+            .map(|c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+            .collect());
+    }
+
+    parse_key(line.trim()).map(|x| vec![x])
+}
+
+pub fn parse_key(text: &str) -> Result<KeyEvent, String> {
+    let mut rest = text;
+    let mut modifiers = KeyModifiers::NONE;
+
+    // a lone "-" is a key, not a modifier separator
+    'outer: while rest.len() > 1 {
+        for (prefix, modifier) in MODIFIERS.iter() {
+            if let Some(stripped) = rest.strip_prefix(prefix) {
+                if !stripped.is_empty() {
+                    modifiers |= *modifier;
+                    rest = stripped;
+                    continue 'outer;
+                }
+            }
+        }
+
+        break;
+    }
+
+    let code = match NAMED_KEYS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(rest))
+    {
+        Some((_, code)) => *code,
+        None => {
+            let mut chars = rest.chars();
+
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return Err(format!("unknown key: {}", text)),
+            }
+        }
+    };
+
+    Ok(KeyEvent::new(code, modifiers))
+}
+
+pub fn format_key(key: &KeyEvent) -> Option<String> {
+    let mut result = String::new();
+
+    for (prefix, modifier) in MODIFIERS.iter() {
+        if key.modifiers.contains(*modifier) {
+            result.push_str(prefix);
+        }
+    }
+
+    match NAMED_KEYS.iter().find(|(_, code)| *code == key.code) {
+        Some((name, _)) => result.push_str(name),
+        None => match key.code {
+            KeyCode::Char(c) => result.push(c),
+            _ => return None,
+        },
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(
+            parse_key("a"),
+            Ok(key(KeyCode::Char('a'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            parse_key("Enter"),
+            Ok(key(KeyCode::Enter, KeyModifiers::NONE))
+        );
+        assert_eq!(parse_key("esc"), Ok(key(KeyCode::Esc, KeyModifiers::NONE)));
+        assert_eq!(
+            parse_key("Ctrl-o"),
+            Ok(key(KeyCode::Char('o'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            parse_key("Alt-Enter"),
+            Ok(key(KeyCode::Enter, KeyModifiers::ALT))
+        );
+        assert_eq!(
+            parse_key("-"),
+            Ok(key(KeyCode::Char('-'), KeyModifiers::NONE))
+        );
+        assert!(parse_key("Hyper-x").is_err());
+    }
+
+    #[test]
+    fn parses_text_lines() {
+        let keys = parse_line("text a b").unwrap();
+        let chars: Vec<KeyCode> = keys.iter().map(|x| x.code).collect();
+
+        assert_eq!(
+            chars,
+            vec![KeyCode::Char('a'), KeyCode::Char(' '), KeyCode::Char('b')]
+        );
+        assert!(parse_line("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn formats_what_it_parses() {
+        for text in [
+            "x",
+            "Space",
+            "Ctrl-e",
+            "Alt-y",
+            "Ctrl-Alt-Down",
+            "PageUp",
+            "-",
+        ] {
+            let key = parse_key(text).unwrap();
+            assert_eq!(format_key(&key).as_deref(), Some(text));
+        }
+    }
+
+    #[test]
+    fn fails_once_the_replay_is_over() {
+        let mut events = ReplayEvents::new(vec![key(KeyCode::Enter, KeyModifiers::NONE)]);
+
+        assert!(matches!(
+            events.next_event(Duration::ZERO),
+            Ok(Some(Event::Key(_)))
+        ));
+
+        let error = events.next_event(Duration::ZERO).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
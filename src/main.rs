@@ -2,67 +2,169 @@
 extern crate lazy_static;
 
 use crate::app::domain::LaunchModel;
+use crate::app::opener::Opener;
+use crate::commands::Subcommand;
+use crate::config::{ClipboardConfig, Config};
+use crate::events::{EventSource, RecordEvents, ReplayEvents, TerminalEvents};
 use crate::terminal::TerminalState;
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{Event, KeyCode, KeyModifiers};
+use shared::runner::{self, PluginCommand, PluginResult};
+use shared::serialization::{Builtin, Format};
 
 use std::{env::consts::FAMILY, io::BufReader, process::Output};
 use structopt::StructOpt;
 
 use tui::{
-    backend::Backend,
+    backend::{Backend, TestBackend},
     layout::{Alignment, Constraint, Corner, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{List, ListItem, Paragraph, Tabs},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Tabs, Wrap},
     Frame, Terminal,
 };
 
+use std::collections::BTreeMap;
+use std::ffi::OsString;
 use std::fs::File;
 use std::path::PathBuf;
 
 use std::env;
 use std::io::{self, Write};
-use std::process::Command;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
 
 mod app;
-use app::{domain::GroupModel, model::AppModel, state::State, stateful::StatefulList};
+use app::{
+    domain::GroupModel, domain::SelectableItemModel, model::AppModel, state::State,
+    stateful::StatefulList,
+};
 
+mod clipboard;
+mod commands;
+mod config;
+mod events;
 mod terminal;
 
 lazy_static! {}
 
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const HEADLESS_SIZE: (u16, u16) = (80, 24);
+
 #[derive(Debug, StructOpt)]
 pub struct Options {
     #[structopt(parse(from_os_str))]
     target: Option<PathBuf>,
+    #[structopt(
+        long,
+        short,
+        help = "json, yaml or toml; detected from the file extension or content if omitted"
+    )]
+    format: Option<Format>,
+    #[structopt(long, short, help = "Runs the plugins of a profile from the config")]
+    profile: Option<String>,
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "Runs a plugin command line, quoted like in a shell, can be repeated to run several in parallel"
+    )]
+    run: Vec<String>,
+    #[structopt(long, help = "Seconds to wait for each plugin")]
+    timeout: Option<u64>,
+    #[structopt(long, help = "Print the resolved command instead of running it")]
+    dry_run: bool,
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Defaults to justlist.toml in the config directory"
+    )]
+    config: Option<PathBuf>,
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Plays back keys from a file, one per line, before reading the terminal"
+    )]
+    replay: Option<PathBuf>,
+    #[structopt(long, parse(from_os_str), help = "Writes the pressed keys to a file")]
+    record: Option<PathBuf>,
+    #[structopt(subcommand)]
+    command: Option<Subcommand>,
 }
 
-fn read_app_model(options: Options) -> AppModel {
+fn read_app_model(options: Options, config: Config) -> AppModel {
     let app: AppModel;
+    let opener = Opener::new(config.opener);
 
-    if options.target.is_some() {
+    if options.profile.is_some() || !options.run.is_empty() {
+        app = AppModel::empty(opener);
+    } else if options.target.is_some() {
         let file_path: PathBuf = options.target.unwrap();
+        let format = options.format.or_else(|| Format::from_path(&file_path));
         let reader = BufReader::new(File::open(file_path).unwrap());
-        app = AppModel::new(reader);
+        app = AppModel::new(reader, format, opener);
     } else {
         let stdin = std::io::stdin();
         let handle = stdin.lock();
         let reader = BufReader::new(handle);
-        app = AppModel::new(reader);
+        app = AppModel::new(reader, options.format, opener);
     };
 
     app
 }
 
 fn main() -> std::io::Result<()> {
-    let options = Options::from_args();
-    let app = read_app_model(options);
+    let mut options = Options::from_args();
+
+    if let Some(command) = options.command.take() {
+        return commands::execute(command);
+    }
 
-    let result = _main(app);
+    let config_path = options.config.clone().unwrap_or_else(Config::default_path);
+    let config = Config::load(&config_path)?;
+    let clipboard = config.clipboard.clone();
+    let dry_run = options.dry_run;
+
+    let timeout = options.timeout.map(Duration::from_secs);
+
+    let updates = match &options.profile {
+        Some(name) => {
+            let profile = config.profiles.get(name).ok_or_else(|| {
+                let message = format!("unknown profile: {}", name);
+                io::Error::new(io::ErrorKind::NotFound, message)
+            })?;
+
+            let timeout = timeout.or(profile.timeout.map(Duration::from_secs));
+            Some(runner::spawn_all(&profile.plugins, timeout))
+        }
+        None if !options.run.is_empty() => {
+            let plugins = options
+                .run
+                .iter()
+                .map(|x| PluginCommand::parse(x))
+                .collect::<io::Result<Vec<PluginCommand>>>()?;
+
+            Some(runner::spawn_all(&plugins, timeout))
+        }
+        None => None,
+    };
+
+    // a replay without a terminal to show it on renders off screen, which is enough for tests
+    let headless = options.replay.is_some() && atty::isnt(atty::Stream::Stdout);
+    let events = create_event_source(&options, headless)?;
+
+    let app = read_app_model(options, config);
+
+    let result = _main(app, updates, &clipboard, events, headless, dry_run);
 
     match result {
+        Ok(launch) if dry_run => {
+            for line in launch.describe() {
+                println!("{}", line);
+            }
+        }
         Ok(launch) => {
-            execute_launch(launch);
+            let code = execute_launch(launch);
+            std::process::exit(code);
         }
         Err(error) => {
             print!("{}", error);
@@ -71,90 +173,279 @@ fn main() -> std::io::Result<()> {
     Ok(())
 }
 
-fn execute_launch(launch: LaunchModel) {
-    if launch.executable.is_none() {
-        return;
+fn execute_launch(launch: LaunchModel) -> i32 {
+    let command = launch.command_line();
+
+    if command.is_empty() {
+        return 0;
     }
 
     let LaunchModel {
-        executable,
-        param,
         is_terminal,
+        cwd,
+        env,
+        ..
     } = launch;
 
-    let executable = &executable.unwrap();
-    let param = &param.unwrap();
+    let context = LaunchContext { cwd, env };
 
-    if FAMILY == "windows" {
-        launch_windows(executable, param);
-        return;
+    if is_terminal {
+        return match launch_inplace(&command, &context) {
+            Ok(status) => exit_code(status),
+            Err(error) => {
+                eprintln!("{}: {}", command[0].to_string_lossy(), error);
+                spawn_error_code(&error)
+            }
+        };
     }
 
-    let child_result = if is_terminal {
-        launch_inplace(executable, param)
-    } else {
-        launch_external(executable, param)
-    };
+    if FAMILY == "windows" {
+        return match launch_windows(&command, &context) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("{}: {}", command[0].to_string_lossy(), error);
+                spawn_error_code(&error)
+            }
+        };
+    }
 
-    match child_result {
-        Err(error) => print!("{}", error.to_string()),
+    match launch_external(&command, &context) {
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
         Ok(output) => {
-            println!("status: {}", output.status);
             io::stdout().write_all(&output.stdout).unwrap();
             io::stderr().write_all(&output.stderr).unwrap();
+            exit_code(output.status)
         }
     }
 }
 
-fn launch_windows(exec: &str, param: &str) {
-    prepare_command(exec).arg(param).spawn();
+#[cfg(target_family = "unix")]
+fn exit_code(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    // mimic the shell, which reports a signal as 128 + its number
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
 }
 
-fn launch_inplace(exec: &str, param: &str) -> io::Result<Output> {
-    prepare_command(exec).arg(param).output()
+#[cfg(target_family = "windows")]
+fn exit_code(status: ExitStatus) -> i32 {
+    status.code().unwrap_or(1)
 }
 
-fn prepare_command(exec: &str) -> Command {
-    let mut parts = exec.split_whitespace();
+// like the launcher and the shell: 127 for a missing command, 126 for one that can't be run
+fn spawn_error_code(error: &io::Error) -> i32 {
+    match error.kind() {
+        io::ErrorKind::NotFound => 127,
+        io::ErrorKind::PermissionDenied => 126,
+        _ => 1,
+    }
+}
 
-    let first = parts.next();
-    let first_part = first.unwrap();
+struct LaunchContext {
+    cwd: Option<String>,
+    env: BTreeMap<String, String>,
+}
 
-    let mut cmd = Command::new(first_part);
+impl LaunchContext {
+    fn apply<'a>(&self, cmd: &'a mut Command) -> &'a mut Command {
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
 
-    for part in parts {
-        cmd.arg(part);
+        cmd.envs(&self.env)
     }
+}
 
+fn launch_windows(command: &[OsString], context: &LaunchContext) -> io::Result<()> {
+    context.apply(&mut prepare_command(command)).spawn()?;
+    Ok(())
+}
+
+fn launch_inplace(command: &[OsString], context: &LaunchContext) -> io::Result<ExitStatus> {
+    context
+        .apply(&mut prepare_command(command))
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+}
+
+fn prepare_command(command: &[OsString]) -> Command {
+    let mut cmd = Command::new(&command[0]);
+    cmd.args(&command[1..]);
     cmd
 }
 
-fn launch_external(exec: &str, param: &str) -> io::Result<Output> {
+fn launch_external(command: &[OsString], context: &LaunchContext) -> io::Result<Output> {
     let mut launcher_command = env::current_exe().unwrap();
     launcher_command.pop();
     launcher_command.push("launcher");
 
-    let formatted = format!("{exec} {param}");
+    // the arguments go separately, so the param keeps its spaces and exact bytes
+    context
+        .apply(&mut Command::new(launcher_command))
+        .arg(&command[0])
+        .arg("--")
+        .args(&command[1..])
+        .output()
+}
+
+fn create_event_source(options: &Options, headless: bool) -> io::Result<Box<dyn EventSource>> {
+    let events: Box<dyn EventSource> = match &options.replay {
+        Some(path) if headless => Box::new(ReplayEvents::from_file(path)?),
+        Some(path) => Box::new(ReplayEvents::from_file(path)?.then_terminal()),
+        None => Box::new(TerminalEvents),
+    };
 
-    Command::new(launcher_command).arg(formatted).output()
+    match &options.record {
+        Some(path) => Ok(Box::new(RecordEvents::new(events, path)?)),
+        None => Ok(events),
+    }
 }
 
-fn _main(app: AppModel) -> io::Result<LaunchModel> {
-    let mut terminal_state = TerminalState::new();
+fn _main(
+    app: AppModel,
+    updates: Option<Receiver<PluginResult>>,
+    clipboard: &ClipboardConfig,
+    mut events: Box<dyn EventSource>,
+    headless: bool,
+    dry_run: bool,
+) -> io::Result<LaunchModel> {
     let state = State::new(&app.groups);
-    return run_app(&mut terminal_state.terminal, app, state);
+
+    if headless {
+        let (width, height) = HEADLESS_SIZE;
+        let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+        let launch = run_app(&mut terminal, &mut events, app, state, updates)?;
+
+        if let (Some(text), Some(command), false) = (&launch.clipboard, &clipboard.command, dry_run)
+        {
+            clipboard::copy_with(command, text)?;
+        }
+
+        return Ok(launch);
+    }
+
+    let mut terminal_state = TerminalState::new();
+    let launch = run_app(
+        &mut terminal_state.terminal,
+        &mut events,
+        app,
+        state,
+        updates,
+    )?;
+
+    // a dry run only describes the copy
+    if let (Some(text), false) = (&launch.clipboard, dry_run) {
+        match &clipboard.command {
+            Some(command) => clipboard::copy_with(command, text)?,
+            None => terminal_state.copy_to_clipboard(text),
+        }
+    }
+
+    Ok(launch)
+}
+
+fn receive_updates(app: &mut AppModel, state: &mut State, receiver: &Receiver<PluginResult>) {
+    loop {
+        match receiver.try_recv() {
+            Ok(result) => {
+                let source = result.source;
+                let groups = result.into_groups();
+                let (position, count) = app.insert_groups(source, &groups);
+                state.insert_groups(position, &app.groups[position..position + count]);
+                state.update_lengths(&app.groups);
+            }
+            Err(TryRecvError::Empty) => return,
+            // every plugin has reported
+            Err(TryRecvError::Disconnected) => {
+                state.is_loading = false;
+                return;
+            }
+        }
+    }
 }
 
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
-    app: AppModel,
+    events: &mut impl EventSource,
+    mut app: AppModel,
     mut state: State,
+    updates: Option<Receiver<PluginResult>>,
 ) -> std::io::Result<LaunchModel> {
+    state.is_loading = updates.is_some();
+
     loop {
+        if let Some(receiver) = &updates {
+            receive_updates(&mut app, &mut state, receiver);
+        }
+
         Terminal::draw(terminal, |f: &mut tui::Frame<B>| ui(f, &app, &mut state))?;
 
-        if let Event::Key(key) = event::read()? {
+        if let Some(Event::Key(key)) = events.next_event(POLL_INTERVAL)? {
+            if app.groups.is_empty() {
+                if key.code == KeyCode::Esc {
+                    return Ok(LaunchModel::default());
+                }
+
+                continue;
+            }
+
+            if state.explain.is_some() {
+                state.explain = None;
+                continue;
+            }
+
+            if let Some(menu) = state.action_menu.as_mut() {
+                match key.code {
+                    KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let action_index = menu.get_selected();
+                        state.explain = Some(explain(app.handle_action(&state, action_index)));
+                    }
+                    KeyCode::Down => menu.next(),
+                    KeyCode::Up => menu.previous(),
+                    KeyCode::Esc => state.close_action_menu(),
+                    KeyCode::Enter => {
+                        let action_index = menu.get_selected();
+
+                        if let Some(launch) = app.handle_action(&state, action_index) {
+                            return Ok(launch);
+                        }
+                    }
+                    _ => {}
+                }
+
+                continue;
+            }
+
+            let group_index = state.get_selected_group();
+
             match key.code {
+                KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    state.open_action_menu(&app.groups[group_index].actions)
+                }
+                KeyCode::Enter if key.modifiers.contains(KeyModifiers::ALT) => {
+                    state.open_action_menu(&app.groups[group_index].actions)
+                }
+                KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    state.explain = Some(explain(app.handle_enter(&state)));
+                }
+                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    if let Some(launch) = app.handle_copy(&state, Builtin::Copy) {
+                        return Ok(launch);
+                    }
+                }
+                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    if let Some(launch) = app.handle_copy(&state, Builtin::CopyLabel) {
+                        return Ok(launch);
+                    }
+                }
                 KeyCode::Left => state.groups.previous(),
                 KeyCode::Right => state.groups.next(),
                 KeyCode::Down => state.select_item_next(),
@@ -168,9 +459,11 @@ fn run_app<B: Backend>(
 
                     state.handle_escape();
                 }
+                KeyCode::Enter if !app.is_launchable(&state) => {}
                 KeyCode::Enter => {
-                    let launch = app.handle_enter(&state);
-                    return Ok(launch);
+                    if let Some(launch) = app.handle_enter(&state) {
+                        return Ok(launch);
+                    }
                 }
                 _ => return Ok(LaunchModel::default()),
             }
@@ -178,7 +471,27 @@ fn run_app<B: Backend>(
     }
 }
 
+// the filter may match no row, or the group has no items at all
+fn explain(launch: Option<LaunchModel>) -> Vec<String> {
+    match launch {
+        Some(launch) => launch.describe(),
+        None => vec![String::from("nothing selected")],
+    }
+}
+
 fn ui<B: Backend>(f: &mut Frame<B>, app: &AppModel, state: &mut State) {
+    if app.groups.is_empty() {
+        let message = if state.is_loading {
+            "Loading, Esc to exit"
+        } else {
+            "No results, Esc to exit"
+        };
+
+        let text = Paragraph::new(Spans::from(message));
+        f.render_widget(text, f.size());
+        return;
+    }
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
@@ -196,10 +509,82 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &AppModel, state: &mut State) {
     render_input(f, state, chunks[1]);
     render_list(f, app, state, chunks[2]);
     render_prompt(f, chunks[3]);
+
+    if state.is_action_menu_open() {
+        render_action_menu(f, app, state, chunks[2]);
+    }
+
+    if let Some(lines) = &state.explain {
+        render_explain(f, lines, chunks[2]);
+    }
+}
+
+fn popup_area(chunk: Rect, width: u16, height: u16) -> Rect {
+    Rect::new(
+        chunk.x + chunk.width.saturating_sub(width) / 2,
+        chunk.y + chunk.height.saturating_sub(height) / 2,
+        width.min(chunk.width),
+        height.min(chunk.height),
+    )
+}
+
+fn render_explain<B: Backend>(f: &mut Frame<B>, lines: &[String], chunk: Rect) {
+    let width = lines.iter().map(|x| x.len()).max().unwrap_or(0) as u16 + 4;
+    let height = lines.len() as u16 + 2;
+    let area = popup_area(chunk, width, height);
+
+    let text: Vec<Spans> = lines.iter().map(|x| Spans::from(x.as_str())).collect();
+    let paragraph = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title("explain"))
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn render_action_menu<B: Backend>(
+    f: &mut Frame<B>,
+    app: &AppModel,
+    state: &mut State,
+    chunk: Rect,
+) {
+    let actions = &app.groups[state.get_selected_group()].actions;
+
+    let items: Vec<ListItem> = actions
+        .iter()
+        .map(|action| ListItem::new(action.name.as_str()))
+        .collect();
+
+    let width = actions
+        .iter()
+        .map(|x| x.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(8) as u16
+        + 6;
+    let height = actions.len() as u16 + 2;
+    let area = popup_area(chunk, width, height);
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("actions"))
+        .highlight_style(
+            Style::default()
+                .fg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    if let Some(menu) = state.action_menu.as_mut() {
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut menu.state);
+    }
 }
 
 fn render_prompt<B: Backend>(f: &mut Frame<B>, chunk: Rect) {
-    let text = Paragraph::new(Spans::from("Esc to exit")).alignment(Alignment::Left);
+    let text = Paragraph::new(Spans::from(
+        "Esc to exit, Ctrl-O for actions, Ctrl-Y to copy, Ctrl-E to explain",
+    ))
+    .alignment(Alignment::Left);
     f.render_widget(text, chunk);
 }
 
@@ -242,6 +627,34 @@ fn render_list<B: Backend>(f: &mut Frame<B>, app: &AppModel, state: &mut State,
     f.render_stateful_widget(list, chunk, list_state);
 }
 
+fn create_item_spans(item: &SelectableItemModel) -> Spans<'_> {
+    let mut spans = Vec::with_capacity(4);
+
+    if let Some(icon) = &item.icon {
+        spans.push(Span::raw(format!("{} ", icon)));
+    }
+
+    spans.push(Span::raw(item.label.as_str()));
+
+    if let Some(description) = &item.description {
+        spans.push(Span::styled(
+            format!("  {}", description),
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::DIM),
+        ));
+    }
+
+    for tag in item.tags.iter() {
+        spans.push(Span::styled(
+            format!(" #{}", tag),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    Spans::from(spans)
+}
+
 fn create_list<'b, 'a: 'b>(app: &'a AppModel, state: &'b mut State) -> List<'a> {
     let selected_group_index = state.get_selected_group();
     let list = &app.groups[selected_group_index].items;
@@ -249,11 +662,12 @@ fn create_list<'b, 'a: 'b>(app: &'a AppModel, state: &'b mut State) -> List<'a>
 
     let mut index = 0;
     for item in list {
-        if !item.label.contains(state.dump_input()) {
+        if !item.is_match(state.dump_input()) {
             continue;
         }
 
-        let list_item = ListItem::new(item.label.as_str()).style(Style::default().fg(Color::White));
+        let list_item =
+            ListItem::new(create_item_spans(item)).style(Style::default().fg(Color::White));
 
         list_items.push(list_item);
         state.map_index(item.index, index);
@@ -271,3 +685,394 @@ fn create_list<'b, 'a: 'b>(app: &'a AppModel, state: &'b mut State) -> List<'a>
 
     list
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OpenerConfig;
+    use crossterm::event::KeyEvent;
+    use shared::serialization::Groups;
+    use std::sync::mpsc;
+    use tui::buffer::Buffer;
+
+    const WIDTH: u16 = 72;
+    const HEIGHT: u16 = 8;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn typed(text: &str) -> Vec<KeyEvent> {
+        text.chars().map(|c| key(KeyCode::Char(c))).collect()
+    }
+
+    fn test_app() -> AppModel {
+        let file = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/src/test.json")).unwrap();
+        AppModel::new(file, None, Opener::new(OpenerConfig::default()))
+    }
+
+    fn run(app: AppModel, keys: Vec<KeyEvent>) -> (io::Result<LaunchModel>, Terminal<TestBackend>) {
+        run_with_updates(app, keys, None)
+    }
+
+    fn run_with_updates(
+        app: AppModel,
+        keys: Vec<KeyEvent>,
+        updates: Option<Receiver<PluginResult>>,
+    ) -> (io::Result<LaunchModel>, Terminal<TestBackend>) {
+        let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+        let mut events = ReplayEvents::new(keys);
+        let state = State::new(&app.groups);
+
+        let result = run_app(&mut terminal, &mut events, app, state, updates);
+
+        (result, terminal)
+    }
+
+    fn empty_app() -> AppModel {
+        AppModel::empty(Opener::new(OpenerConfig::default()))
+    }
+
+    fn launch(app: AppModel, keys: Vec<KeyEvent>) -> LaunchModel {
+        run(app, keys).0.unwrap()
+    }
+
+    // the keys must leave justlist running, the snapshot is the frame drawn after the last one
+    fn render(app: AppModel, keys: Vec<KeyEvent>) -> Vec<String> {
+        let (result, terminal) = run(app, keys);
+
+        assert_eq!(
+            result.err().map(|x| x.kind()),
+            Some(io::ErrorKind::UnexpectedEof)
+        );
+
+        buffer_lines(terminal.backend().buffer())
+    }
+
+    fn buffer_lines(buffer: &Buffer) -> Vec<String> {
+        let width = buffer.area.width as usize;
+
+        buffer
+            .content
+            .chunks(width)
+            .map(|row| {
+                let line: String = row.iter().map(|cell| cell.symbol.as_str()).collect();
+                line.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renders_the_first_group() {
+        let lines = render(test_app(), vec![]);
+
+        assert_eq!(
+            lines,
+            vec![
+                " files",
+                "",
+                "> mod.rs",
+                "  serialization.rs",
+                "  Terminal_X.rs",
+                "  main.rs",
+                "  stateful.rs",
+                "Esc to exit, Ctrl-O for actions, Ctrl-Y to copy, Ctrl-E to explain",
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_the_list_while_typing() {
+        let lines = render(test_app(), typed("stat"));
+
+        assert_eq!(
+            lines,
+            vec![
+                " files",
+                "stat",
+                "> stateful.rs",
+                "  state.rs",
+                "  stateful.rs",
+                "  state.rs",
+                "  stateful.rs",
+                "Esc to exit, Ctrl-O for actions, Ctrl-Y to copy, Ctrl-E to explain",
+            ]
+        );
+    }
+
+    #[test]
+    fn moves_the_selection_within_the_filtered_list() {
+        let mut keys = typed("at");
+        keys.extend(vec![key(KeyCode::Down), key(KeyCode::Down)]);
+
+        let lines = render(test_app(), keys);
+
+        assert_eq!(lines[2], "  serialization.rs");
+        assert_eq!(lines[3], "  stateful.rs");
+        assert_eq!(lines[4], "> state.rs");
+    }
+
+    #[test]
+    fn clears_the_filter_on_escape() {
+        let mut keys = typed("main");
+        keys.push(key(KeyCode::Esc));
+
+        let lines = render(test_app(), keys);
+
+        assert_eq!(lines[1], "");
+        assert_eq!(lines[2], "> mod.rs");
+    }
+
+    #[test]
+    fn shows_the_explain_popup() {
+        let lines = render(test_app(), vec![ctrl('e')]);
+
+        assert_eq!(
+            lines,
+            vec![
+                " files",
+                "",
+                ">┌explain────────────────────────────────────────────────────────────┐",
+                " │command: xed /home/amzak/projects/rust/justlist/src/shared/mod.rs  │",
+                " │cwd: (inherited)                                                   │",
+                " │terminal: false, detached with launcher                            │",
+                " └───────────────────────────────────────────────────────────────────┘",
+                "Esc to exit, Ctrl-O for actions, Ctrl-Y to copy, Ctrl-E to explain",
+            ]
+        );
+    }
+
+    #[test]
+    fn explains_that_nothing_is_selected() {
+        let mut keys = typed("zzz");
+        keys.push(ctrl('e'));
+
+        let lines = render(test_app(), keys);
+
+        assert!(lines.iter().any(|x| x.contains("│nothing selected")));
+    }
+
+    #[test]
+    fn keeps_the_action_menu_open_when_nothing_is_selected() {
+        let document = r#"{"groups": [{
+            "label": "files",
+            "items": [{"label": "main.rs", "param": "src/main.rs"}],
+            "actions": [{"name": "edit", "command_template": "vim"}]
+        }]}"#;
+        let app = AppModel::new(
+            document.as_bytes(),
+            None,
+            Opener::new(OpenerConfig::default()),
+        );
+
+        let mut keys = typed("zzz");
+        keys.extend(vec![ctrl('o'), key(KeyCode::Enter), ctrl('e')]);
+
+        let lines = render(app, keys);
+
+        assert!(lines.iter().any(|x| x.contains("│nothing selected")));
+    }
+
+    #[test]
+    fn shows_loading_until_groups_arrive() {
+        let (_sender, receiver) = mpsc::channel();
+        let (_, terminal) =
+            run_with_updates(empty_app(), vec![key(KeyCode::Enter)], Some(receiver));
+        let lines = buffer_lines(terminal.backend().buffer());
+
+        assert_eq!(lines[0], "Loading, Esc to exit");
+        assert!(lines[1..].iter().all(|x| x.is_empty()));
+    }
+
+    #[test]
+    fn shows_no_results_once_every_plugin_finished_empty() {
+        let (sender, receiver) = mpsc::channel();
+        sender
+            .send(PluginResult {
+                source: 0,
+                command: String::from("search"),
+                groups: Ok(Groups::new()),
+            })
+            .unwrap();
+        drop(sender);
+
+        let (_, terminal) = run_with_updates(empty_app(), vec![], Some(receiver));
+
+        assert_eq!(
+            buffer_lines(terminal.backend().buffer())[0],
+            "No results, Esc to exit"
+        );
+    }
+
+    #[test]
+    fn adds_the_batches_of_a_plugin_to_its_tab() {
+        let (sender, receiver) = mpsc::channel();
+        let batches = [
+            (
+                0,
+                r#"{"groups": [{"label": "files", "items": [{"label": "a", "param": "a"}]}]}"#,
+            ),
+            (
+                1,
+                r#"{"groups": [{"label": "repos", "items": [{"label": "r", "param": "r"}]}]}"#,
+            ),
+            (
+                0,
+                r#"{"groups": [{"label": "files", "items": [{"label": "b", "param": "b"}]}]}"#,
+            ),
+        ];
+
+        for (source, batch) in batches {
+            sender
+                .send(PluginResult {
+                    source,
+                    command: String::from("search"),
+                    groups: Groups::parse(batch, None),
+                })
+                .unwrap();
+        }
+
+        let keys = vec![key(KeyCode::Down), key(KeyCode::Enter)];
+        let (result, terminal) = run_with_updates(empty_app(), keys, Some(receiver));
+        let lines = buffer_lines(terminal.backend().buffer());
+
+        assert_eq!(lines[0], " files │ repos");
+        assert_eq!(result.unwrap().param.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn keeps_running_on_enter_over_an_error() {
+        let (sender, receiver) = mpsc::channel();
+        sender
+            .send(PluginResult {
+                source: 0,
+                command: String::from("search"),
+                groups: Err(io::Error::other("no such file")),
+            })
+            .unwrap();
+
+        let keys = vec![key(KeyCode::Enter)];
+        let (result, terminal) = run_with_updates(empty_app(), keys, Some(receiver));
+        let lines = buffer_lines(terminal.backend().buffer());
+
+        // still running when the script runs out
+        assert_eq!(
+            result.err().map(|x| x.kind()),
+            Some(io::ErrorKind::UnexpectedEof)
+        );
+        assert_eq!(lines[0], " search: error");
+        assert_eq!(lines[2], "> no such file");
+    }
+
+    #[test]
+    fn keeps_running_on_enter_in_an_empty_group() {
+        let document = r#"{"groups": [{"label": "search", "items": []}]}"#;
+        let app = AppModel::new(
+            document.as_bytes(),
+            None,
+            Opener::new(OpenerConfig::default()),
+        );
+        let lines = render(app, vec![key(KeyCode::Enter)]);
+
+        assert_eq!(lines[0], " search");
+        assert_eq!(lines[2], "");
+    }
+
+    #[test]
+    fn exits_on_escape_while_loading() {
+        let launch = launch(empty_app(), vec![key(KeyCode::Esc)]);
+
+        assert!(launch.executable.is_none());
+    }
+
+    #[test]
+    fn launches_the_selected_item() {
+        let keys = vec![key(KeyCode::Down), key(KeyCode::Enter)];
+        let launch = launch(test_app(), keys);
+
+        assert_eq!(launch.executable.as_deref(), Some("xed"));
+        assert_eq!(
+            launch.param.as_deref(),
+            Some("/home/amzak/projects/rust/justlist/src/shared/serialization.rs")
+        );
+        assert!(!launch.is_terminal);
+    }
+
+    // the selection is an index into the filtered list, it has to be mapped back to the group
+    #[test]
+    fn launches_the_filtered_item() {
+        let mut keys = typed("at");
+        keys.extend(vec![
+            key(KeyCode::Down),
+            key(KeyCode::Down),
+            key(KeyCode::Enter),
+        ]);
+
+        let launch = launch(test_app(), keys);
+
+        assert_eq!(
+            launch.param.as_deref(),
+            Some("/home/amzak/projects/rust/justlist/src/app/state.rs")
+        );
+    }
+
+    #[test]
+    fn launches_the_first_item_after_the_filter_changes() {
+        let mut keys = vec![key(KeyCode::Down), key(KeyCode::Down)];
+        keys.extend(typed("main"));
+        keys.push(key(KeyCode::Enter));
+
+        let launch = launch(test_app(), keys);
+
+        assert_eq!(
+            launch.param.as_deref(),
+            Some("/home/amzak/projects/rust/justlist/src/main.rs")
+        );
+    }
+
+    #[test]
+    fn exits_on_escape_with_an_empty_filter() {
+        let mut keys = typed("main");
+        keys.extend(vec![key(KeyCode::Esc), key(KeyCode::Esc)]);
+
+        let launch = launch(test_app(), keys);
+
+        assert!(launch.executable.is_none());
+        assert!(launch.clipboard.is_none());
+    }
+
+    #[test]
+    fn copies_the_selected_param() {
+        let launch = launch(test_app(), vec![ctrl('y')]);
+
+        assert!(launch.executable.is_none());
+        assert_eq!(
+            launch.clipboard.as_deref(),
+            Some("/home/amzak/projects/rust/justlist/src/shared/mod.rs")
+        );
+    }
+
+    #[test]
+    fn copies_the_label_as_shown() {
+        let mut keys = typed("terminal");
+        keys.push(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::ALT));
+
+        let launch = launch(test_app(), keys);
+
+        assert_eq!(launch.clipboard.as_deref(), Some("Terminal_X.rs"));
+    }
+
+    #[test]
+    fn copies_nothing_when_the_filter_matches_no_row() {
+        let mut keys = typed("zzz");
+        keys.push(ctrl('y'));
+
+        let lines = render(test_app(), keys);
+
+        assert_eq!(lines[2], "");
+    }
+}
@@ -1,24 +1,48 @@
 use model::Response;
 use serde::Deserialize;
-use shared::plugin::JustListPlugin;
+use shared::plugin::{self, Describe, JustListPlugin, PluginDescription};
 use shared::serialization::{ListGroup, SelectableItem};
 use shared::{plugin::JustListAction, serialization::Groups};
+use std::io::Read;
+use std::time::Duration;
 use structopt::StructOpt;
 
 pub mod model;
 
 #[derive(Debug, StructOpt)]
+#[structopt(about = "This plugin lists pull requests from a Bitbucket dashboard")]
 struct Options {
     url: String,
     token: String,
     command_template: String,
+    #[structopt(
+        long,
+        default_value = "300",
+        help = "Seconds to reuse the last response for, 0 disables the cache"
+    )]
+    cache: u64,
+}
+
+impl Describe for Options {
+    fn describe() -> PluginDescription {
+        PluginDescription::new(
+            "bb-prs",
+            "This plugin lists pull requests from a Bitbucket dashboard",
+        )
+        .argument("url", "")
+        .argument("token", "")
+        .argument("command-template", "")
+        .option(
+            "cache",
+            "Seconds to reuse the last response for, 0 disables the cache",
+        )
+    }
 }
 
 struct ListPullRequests {}
 
 impl ListPullRequests {
-    fn process_response(response: attohttpc::Response, command: &str) -> ListGroup {
-        let reader = response.text_reader();
+    fn process_response(reader: impl Read, command: &str) -> ListGroup {
         let mut de = serde_json::Deserializer::from_reader(reader);
         let prs = Response::deserialize(&mut de).unwrap();
 
@@ -27,13 +51,28 @@ impl ListPullRequests {
             items: vec![],
             command_template: Some(command.to_owned()),
             is_terminal: Some(false),
+            actions: vec![],
         };
 
         for pull_request in prs.values.iter() {
             let label = format!("[{}] {}", pull_request.author(), pull_request.title);
+            let description = format!(
+                "{} {} -> {}",
+                pull_request.state,
+                pull_request.source_branch(),
+                pull_request.target_branch()
+            );
+
             group.items.push(SelectableItem {
                 label: label,
                 param: pull_request.link().to_string(),
+                description: Some(description),
+                keywords: vec![
+                    pull_request.id.to_string(),
+                    pull_request.source_branch().to_string(),
+                    pull_request.target_branch().to_string(),
+                ],
+                ..Default::default()
             });
         }
 
@@ -49,20 +88,53 @@ impl JustListAction<Options> for ListPullRequests {
 
         match result {
             Ok(response) => {
-                let prs_group =
-                    ListPullRequests::process_response(response, &options.command_template);
+                let prs_group = ListPullRequests::process_response(
+                    response.text_reader(),
+                    &options.command_template,
+                );
                 groups.groups.push(prs_group);
             }
-            Err(err) => println!("{}", err),
+            Err(err) => eprintln!("{}", err),
         }
     }
 }
 
 fn main() -> std::io::Result<()> {
-    let options = Options::from_args();
+    let options: Options = plugin::parse_options()?;
 
-    let plugin = JustListPlugin::new(options);
+    let cache_ttl = Duration::from_secs(options.cache);
+    let plugin = JustListPlugin::new(options).cache_for(cache_ttl);
 
     let action = ListPullRequests {};
     plugin.main(&action)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::plugin::testing;
+
+    #[test]
+    fn lists_pull_requests_of_the_dashboard() {
+        let content = testing::fixture("bitbucket_dash.json");
+        let group = ListPullRequests::process_response(content.as_bytes(), "firefox");
+
+        assert_eq!(group.label, "PR");
+        assert_eq!(group.command_template.as_deref(), Some("firefox"));
+        assert_eq!(group.items.len(), 1);
+
+        let item = &group.items[0];
+        assert_eq!(item.label, "[Tom] Talking Nerdy");
+        assert_eq!(item.param, "http://link/to/pullrequest");
+        assert_eq!(
+            item.description.as_deref(),
+            Some("OPEN feature-ABC-123 -> master")
+        );
+        assert_eq!(item.keywords, vec!["101", "feature-ABC-123", "master"]);
+    }
+
+    #[test]
+    fn describes_every_option() {
+        testing::assert_described::<Options>();
+    }
+}
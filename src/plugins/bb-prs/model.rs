@@ -21,6 +21,11 @@ struct LinkList {
     selfs: Vec<Link>,
 }
 
+#[derive(Serialize, Deserialize)]
+struct Ref {
+    id: String,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct PullRequest {
     pub id: i64,
@@ -29,6 +34,10 @@ pub struct PullRequest {
 
     author: Author,
     links: LinkList,
+    #[serde(rename = "fromRef")]
+    from_ref: Ref,
+    #[serde(rename = "toRef")]
+    to_ref: Ref,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -44,4 +53,18 @@ impl PullRequest {
     pub fn author(&self) -> &str {
         return &self.author.user.displayName;
     }
+
+    pub fn source_branch(&self) -> &str {
+        self.from_ref.branch()
+    }
+
+    pub fn target_branch(&self) -> &str {
+        self.to_ref.branch()
+    }
+}
+
+impl Ref {
+    fn branch(&self) -> &str {
+        self.id.strip_prefix("refs/heads/").unwrap_or(&self.id)
+    }
 }
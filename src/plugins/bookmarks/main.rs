@@ -1,8 +1,9 @@
-use serde::Deserialize;
 use shared::{plugin::JustListAction, serialization::Groups};
+use std::io::Read;
+use std::time::Duration;
 use structopt::StructOpt;
 
-use shared::plugin::JustListPlugin;
+use shared::plugin::{self, Describe, JustListPlugin, PluginDescription};
 
 #[derive(Debug, StructOpt)]
 #[structopt(about = "This plugin gets bookmarks from a JSON file on web")]
@@ -13,15 +14,35 @@ struct Options {
     #[structopt(long, short = "p")]
     password: Option<String>,
     command_template: String,
+    #[structopt(
+        long,
+        default_value = "300",
+        help = "Seconds to reuse the last response for, 0 disables the cache"
+    )]
+    cache: u64,
+}
+
+impl Describe for Options {
+    fn describe() -> PluginDescription {
+        PluginDescription::new("bookmarks", "This plugin gets bookmarks from a JSON file on web")
+            .argument("path", "")
+            .option("user", "")
+            .short('u')
+            .option("password", "")
+            .short('p')
+            .argument("command-template", "")
+            .option(
+                "cache",
+                "Seconds to reuse the last response for, 0 disables the cache",
+            )
+    }
 }
 
 struct Bookmarks {}
 
 impl Bookmarks {
-    fn process_response(response: attohttpc::Response, command: &str) -> Groups {
-        let reader = response.text_reader();
-        let mut de = serde_json::Deserializer::from_reader(reader);
-        let mut groups = Groups::deserialize(&mut de).unwrap();
+    fn process_response(reader: impl Read, command: &str) -> Groups {
+        let mut groups = Groups::from_reader(reader, None).unwrap();
 
         for mut group in groups.groups.iter_mut() {
             group.command_template = Some(String::from(command));
@@ -47,19 +68,46 @@ impl JustListAction<Options> for Bookmarks {
         match result {
             Ok(response) => {
                 let mut received_groups =
-                    Bookmarks::process_response(response, &options.command_template);
+                    Bookmarks::process_response(response.text_reader(), &options.command_template);
                 groups.groups.append(&mut received_groups.groups);
             }
-            Err(err) => println!("{}", err),
+            Err(err) => eprintln!("{}", err),
         }
     }
 }
 
 fn main() -> std::io::Result<()> {
-    let options = Options::from_args();
+    let options: Options = plugin::parse_options()?;
 
-    let plugin = JustListPlugin::new(options);
+    let cache_ttl = Duration::from_secs(options.cache);
+    let plugin = JustListPlugin::new(options).cache_for(cache_ttl);
 
     let action = Bookmarks {};
     plugin.main(&action)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::plugin::testing;
+
+    #[test]
+    fn applies_the_command_template_to_every_group() {
+        let content = testing::fixture("bookmarks.yaml");
+        let groups = Bookmarks::process_response(content.as_bytes(), "firefox");
+
+        assert_eq!(groups.groups.len(), 2);
+
+        for group in groups.groups.iter() {
+            assert_eq!(group.command_template.as_deref(), Some("firefox"));
+        }
+
+        assert_eq!(testing::labels(&groups.groups[0]), vec!["crates.io", "docs.rs"]);
+        assert_eq!(groups.groups[1].items[0].param, "https://github.com");
+    }
+
+    #[test]
+    fn describes_every_option() {
+        testing::assert_described::<Options>();
+    }
+}
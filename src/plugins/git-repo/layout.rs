@@ -0,0 +1,97 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const GIT_DIR: &str = ".git";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// A work tree with its own `.git` directory, or a `.git` file of `--separate-git-dir`.
+    Repository,
+    /// A linked work tree of `git worktree add`, its `.git` file points to the main repo.
+    Worktree,
+    /// A submodule checkout, its `.git` file points into the superproject's `.git/modules`.
+    Submodule,
+    /// A repository without a work tree, usually named `*.git`.
+    Bare,
+}
+
+impl Layout {
+    /// What kind of repository the directory is, if any.
+    pub fn detect(dir: &Path) -> Option<Layout> {
+        let git_path = dir.join(GIT_DIR);
+
+        if git_path.is_dir() {
+            return Some(Layout::Repository);
+        }
+
+        if git_path.is_file() {
+            let git_dir = read_git_file(&git_path)?;
+
+            // only the git dirs of linked work trees share the main one through `commondir`
+            if git_dir.join("commondir").is_file() {
+                return Some(Layout::Worktree);
+            }
+
+            // submodules keep their git dir in `.git/modules` of the superproject, anything
+            // else is a work tree of `git init --separate-git-dir`
+            if is_in_modules(&git_dir) {
+                return Some(Layout::Submodule);
+            }
+
+            return Some(Layout::Repository);
+        }
+
+        if is_bare(dir) {
+            return Some(Layout::Bare);
+        }
+
+        None
+    }
+
+    /// The entry passed to the command: the `.git` directory of a repository, and the directory
+    /// itself when there's no such directory, because `.git` is a file pointing elsewhere or
+    /// the repo is bare.
+    pub fn git_path(&self, dir: &Path) -> PathBuf {
+        let git_path = dir.join(GIT_DIR);
+
+        match self {
+            // a separate git dir leaves only a `.git` file behind, like a worktree
+            Layout::Repository if git_path.is_dir() => git_path,
+            _ => dir.to_path_buf(),
+        }
+    }
+
+    pub fn tag(&self) -> Option<&'static str> {
+        match self {
+            Layout::Repository => None,
+            Layout::Worktree => Some("worktree"),
+            Layout::Submodule => Some("submodule"),
+            Layout::Bare => Some("bare"),
+        }
+    }
+}
+
+// a `.git` file holds `gitdir: <path>`, relative to the work tree or absolute
+fn read_git_file(path: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(path).ok()?;
+    let git_dir = content.trim().strip_prefix("gitdir:")?.trim();
+
+    Some(path.parent()?.join(git_dir))
+}
+
+fn is_in_modules(git_dir: &Path) -> bool {
+    let components: Vec<_> = git_dir.components().map(|x| x.as_os_str()).collect();
+
+    components
+        .windows(2)
+        .any(|pair| pair[0] == GIT_DIR && pair[1] == "modules")
+}
+
+// runs for every directory of the walk, most have no HEAD, which rules them out with one lookup
+fn is_bare(dir: &Path) -> bool {
+    if !dir.join("HEAD").is_file() {
+        return false;
+    }
+
+    dir.join("objects").is_dir() && dir.join("refs").is_dir()
+}
@@ -1,9 +1,16 @@
-use shared::plugin::{JustListAction, JustListPlugin};
+use ignore::{WalkBuilder, WalkState};
+use layout::Layout;
+use shared::plugin::{self, Describe, JustListAction, JustListPlugin, PluginDescription};
 use shared::serialization::*;
+use shared::walk;
+use status::RepoStatus;
 use std::env;
+use std::mem;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
-use walkdir::WalkDir;
+
+pub mod layout;
+pub mod status;
 
 #[derive(Debug, StructOpt)]
 #[structopt(about = "This plugin searches for git repos")]
@@ -15,6 +22,16 @@ struct Options {
     working_dir: Option<PathBuf>,
     #[structopt(long, short)]
     verbose: bool,
+    #[structopt(
+        long,
+        help = "Don't ask git for the branch, upstream and dirty state of each repo"
+    )]
+    no_status: bool,
+    #[structopt(
+        long,
+        help = "Keep looking for repos inside the ones found, e.g. submodules"
+    )]
+    nested: bool,
     #[structopt(
         long = "terminal",
         short = "t",
@@ -23,63 +40,371 @@ struct Options {
     is_terminal: bool,
 }
 
+impl Describe for Options {
+    fn describe() -> PluginDescription {
+        PluginDescription::new("git-repo", "This plugin searches for git repos")
+            .argument("command-template", "")
+            .option("depth", "")
+            .short('d')
+            .option("working-dir", "")
+            .short('w')
+            .flag("verbose", "")
+            .short('v')
+            .flag(
+                "no-status",
+                "Don't ask git for the branch, upstream and dirty state of each repo",
+            )
+            .flag(
+                "nested",
+                "Keep looking for repos inside the ones found, e.g. submodules",
+            )
+            .flag(
+                "terminal",
+                "This flag indicates, that the current terminal should be reused",
+            )
+            .short('t')
+    }
+}
+
 struct SearchGitRepos {}
 
 impl JustListAction<Options> for SearchGitRepos {
     fn execute(&self, groups: &mut Groups, options: &Options) {
+        self.search(groups, options, None);
+    }
+
+    fn execute_streamed(
+        &self,
+        groups: &mut Groups,
+        options: &Options,
+        batch: &mut dyn FnMut(Groups),
+    ) {
+        self.search(groups, options, Some(batch));
+    }
+}
+
+impl SearchGitRepos {
+    // hands the repos found so far to `batch` every now and then, if there is one
+    fn search(
+        &self,
+        groups: &mut Groups,
+        options: &Options,
+        mut batch: Option<&mut dyn FnMut(Groups)>,
+    ) {
         let cwd = env::current_dir().unwrap();
         let working_dir = options.working_dir.as_ref().unwrap_or(&cwd);
         let depth = options.depth;
 
-        let mut group = ListGroup {
-            label: "git repos".to_string(),
-            items: vec![],
-            command_template: Some(options.command_template.to_string()),
-            is_terminal: Some(options.is_terminal),
+        let create_group = |mut items: Vec<SelectableItem>| {
+            items.sort_by(|a, b| a.param.cmp(&b.param));
+
+            ListGroup {
+                label: "git repos".to_string(),
+                items,
+                command_template: Some(options.command_template.to_string()),
+                is_terminal: Some(options.is_terminal),
+                actions: vec![],
+            }
         };
 
-        for item in WalkDir::new(&working_dir).max_depth(depth as usize) {
-            if let Err(_error) = item {
-                if options.verbose {
-                    eprintln!("{}", _error);
+        // repos hide in dot directories and ignored folders as well, so filter nothing
+        // but git's own directories
+        let walker = WalkBuilder::new(working_dir)
+            .max_depth(Some(depth as usize))
+            .standard_filters(false)
+            .filter_entry(|entry| entry.file_name() != layout::GIT_DIR)
+            .build_parallel();
+
+        let mut items = vec![];
+        let mut batches = plugin::Batches::new();
+
+        walk::walk_parallel_pruned(
+            walker,
+            options.verbose,
+            |entry| {
+                let path = entry.path();
+
+                if !entry.file_type().is_some_and(|x| x.is_dir()) {
+                    return (None, WalkState::Continue);
                 }
-                continue;
-            }
 
-            let dir_item = item.unwrap();
-            let path = dir_item.path();
+                let layout = match Layout::detect(path) {
+                    Some(layout) => layout,
+                    None => return (None, WalkState::Continue),
+                };
 
-            if !is_git_repo(path) {
-                continue;
-            }
+                // the `.git` inside a work tree counts towards the depth
+                if layout != Layout::Bare && entry.depth() >= depth as usize {
+                    return (None, WalkState::Continue);
+                }
 
-            if let Some(parent_dir) = path.parent() {
-                let file_path = path.to_str().unwrap();
-                let dir_name = parent_dir.file_name().unwrap().to_str().unwrap();
+                // a bare repo holds no checkouts, only its objects
+                let state = if layout == Layout::Bare || !options.nested {
+                    WalkState::Skip
+                } else {
+                    WalkState::Continue
+                };
 
-                group.items.push(SelectableItem {
-                    label: String::from(dir_name),
-                    param: String::from(file_path),
-                })
-            }
-        }
+                (Some(create_item(path, layout, options)), state)
+            },
+            |item| {
+                items.push(item);
+
+                if let Some(batch) = batch.as_mut() {
+                    if batches.is_due() {
+                        let group = create_group(mem::take(&mut items));
+                        batches.send(groups, group, *batch);
+                    }
+                }
+            },
+        );
 
-        groups.groups.push(group);
+        groups.groups.push(create_group(items));
     }
 }
 
-fn is_git_repo(path: &Path) -> bool {
-    let is_dir = path.is_dir();
-    let file_name = path.file_name().unwrap().to_str().unwrap();
+fn create_item(dir: &Path, layout: Layout, options: &Options) -> SelectableItem {
+    let name = match dir.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => dir.as_os_str().to_string_lossy(),
+    };
+
+    let label = match layout {
+        Layout::Bare => name.strip_suffix(".git").unwrap_or(&name).to_string(),
+        _ => name.to_string(),
+    };
+
+    let item = SelectableItem {
+        tags: layout.tag().map(String::from).into_iter().collect(),
+        ..SelectableItem::for_path(label, &layout.git_path(dir))
+    };
+
+    if options.no_status {
+        return item;
+    }
+
+    let status = match layout {
+        Layout::Bare => RepoStatus::read_head(dir),
+        _ => RepoStatus::read(dir),
+    };
 
-    return is_dir && file_name.ends_with(".git");
+    match status {
+        Ok(status) => SelectableItem {
+            description: Some(status.describe()),
+            tags: item.tags.iter().cloned().chain(status.tags()).collect(),
+            ..item
+        },
+        Err(error) => {
+            if options.verbose {
+                eprintln!("{}: {}", dir.display(), error);
+            }
+            item
+        }
+    }
 }
 
 fn main() -> std::io::Result<()> {
-    let options = Options::from_args();
+    let options: Options = plugin::parse_options()?;
 
     let plugin = JustListPlugin::new(options);
 
     let action = SearchGitRepos {};
     plugin.main(&action)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::plugin::testing::{self, TempDir};
+
+    fn workspace() -> TempDir {
+        TempDir::builder()
+            .dir("justlist/.git")
+            .dir("tools/dotfiles/.git")
+            .file("notes/todo.md", "")
+            .build()
+    }
+
+    fn find_repos(dir: &TempDir, depth: &str) -> Groups {
+        find_repos_with(dir, &["-d", depth])
+    }
+
+    fn find_repos_with(dir: &TempDir, args: &[&str]) -> Groups {
+        let working_dir = dir.path().to_str().unwrap();
+        let mut argv = vec!["git-repo", "code", "-w", working_dir];
+        argv.extend_from_slice(args);
+
+        let options = Options::from_iter(argv);
+        let plugin = JustListPlugin::new(options);
+        testing::run(&plugin, &SearchGitRepos {}, testing::upstream_groups())
+    }
+
+    #[test]
+    fn finds_repos_within_the_depth() {
+        let dir = workspace();
+        let groups = find_repos(&dir, "2");
+
+        assert_eq!(groups.groups.len(), 2);
+        assert_eq!(groups.groups[0].label, "upstream");
+
+        let group = &groups.groups[1];
+        assert_eq!(group.label, "git repos");
+        assert_eq!(testing::labels(group), vec!["justlist"]);
+        assert!(group.items[0].param.ends_with(".git"));
+    }
+
+    #[test]
+    fn streams_the_same_repos() {
+        let dir = workspace();
+        let working_dir = dir.path().to_str().unwrap();
+        let options = Options::from_iter(vec!["git-repo", "code", "-w", working_dir, "-d", "3"]);
+        let plugin = JustListPlugin::new(options).streamed(true);
+        let documents = testing::run_streamed(&plugin, &SearchGitRepos {}, Groups::new());
+
+        let mut labels: Vec<String> = documents
+            .into_iter()
+            .flat_map(|x| x.groups)
+            .flat_map(|x| x.items)
+            .map(|x| x.label)
+            .collect();
+        labels.sort_unstable();
+
+        assert_eq!(labels, vec!["dotfiles", "justlist"]);
+    }
+
+    #[test]
+    fn finds_nested_repos_with_a_larger_depth() {
+        let dir = workspace();
+        let groups = find_repos(&dir, "3");
+
+        assert_eq!(
+            testing::labels(&groups.groups[1]),
+            vec!["dotfiles", "justlist"]
+        );
+    }
+
+    #[test]
+    fn describes_the_state_of_real_repos() {
+        let dir = TempDir::builder().file("app/notes.txt", "").build();
+        let app = dir.path().join("app");
+
+        let init = std::process::Command::new("git")
+            .args(["init", "-q", "-b", "trunk"])
+            .arg(&app)
+            .status();
+
+        // nothing to ask without git
+        if !matches!(init, Ok(status) if status.success()) {
+            return;
+        }
+
+        let groups = find_repos(&dir, "2");
+        let item = &groups.groups[1].items[0];
+
+        assert_eq!(item.label, "app");
+        assert_eq!(
+            item.description.as_deref(),
+            Some("trunk, no commits, dirty")
+        );
+        assert_eq!(item.tags, vec!["dirty"]);
+    }
+
+    fn layouts() -> TempDir {
+        TempDir::builder()
+            .dir("main/.git/worktrees/feature")
+            .file("main/.git/worktrees/feature/commondir", "../..\n")
+            .file("feature/.git", "gitdir: ../main/.git/worktrees/feature\n")
+            .file("main/vendor/lib/.git", "gitdir: ../../.git/modules/lib\n")
+            .file("mirror.git/HEAD", "ref: refs/heads/trunk\n")
+            .dir("mirror.git/objects")
+            .dir("mirror.git/refs/heads")
+            .build()
+    }
+
+    fn tags(group: &ListGroup) -> Vec<(&str, Vec<String>)> {
+        group
+            .items
+            .iter()
+            .map(|x| (x.label.as_str(), x.tags.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn labels_worktrees_and_bare_repos() {
+        let dir = layouts();
+        let groups = find_repos_with(&dir, &["-d", "4", "--no-status"]);
+        let group = &groups.groups[1];
+
+        assert_eq!(
+            tags(group),
+            vec![
+                ("feature", vec![String::from("worktree")]),
+                ("main", vec![]),
+                ("mirror", vec![String::from("bare")]),
+            ]
+        );
+        // a worktree's `.git` is a file, the work tree itself is launched
+        let feature = dir.path().join("feature");
+        assert_eq!(group.items[0].param, feature.to_str().unwrap());
+        assert!(group.items[1].param.ends_with(".git"));
+        assert!(group.items[2].param.ends_with("mirror.git"));
+    }
+
+    #[test]
+    fn finds_submodules_inside_repos_only_when_nested() {
+        let dir = layouts();
+        let groups = find_repos_with(&dir, &["-d", "4", "--no-status", "--nested"]);
+        let group = &groups.groups[1];
+
+        assert_eq!(
+            testing::labels(group),
+            vec!["feature", "lib", "main", "mirror"]
+        );
+
+        let lib = group.items.iter().find(|x| x.label == "lib").unwrap();
+        assert_eq!(lib.tags, vec!["submodule"]);
+        let lib_dir = dir.path().join("main/vendor/lib");
+        assert_eq!(lib.param, lib_dir.to_str().unwrap());
+    }
+
+    #[test]
+    fn tells_submodules_from_separate_git_dirs() {
+        let dir = TempDir::builder()
+            .file("app/.git", "gitdir: ../store/app.git\n")
+            .file("web/.git", "gitdir: ../modules/web.git\n")
+            .file("lib/.git", "gitdir: ../app/.git/modules/lib\n")
+            .build();
+
+        assert_eq!(
+            Layout::detect(&dir.path().join("app")),
+            Some(Layout::Repository)
+        );
+        assert_eq!(
+            Layout::detect(&dir.path().join("web")),
+            Some(Layout::Repository)
+        );
+        assert_eq!(
+            Layout::Repository.git_path(&dir.path().join("web")),
+            dir.path().join("web")
+        );
+        assert_eq!(
+            Layout::detect(&dir.path().join("lib")),
+            Some(Layout::Submodule)
+        );
+    }
+
+    #[test]
+    fn reads_the_branch_of_bare_repos() {
+        let dir = layouts();
+        let groups = find_repos_with(&dir, &["-d", "1"]);
+        let group = &groups.groups[1];
+
+        assert_eq!(testing::labels(group), vec!["mirror"]);
+        assert_eq!(group.items[0].description.as_deref(), Some("trunk"));
+    }
+
+    #[test]
+    fn describes_every_option() {
+        testing::assert_described::<Options>();
+    }
+}
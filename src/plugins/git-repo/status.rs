@@ -0,0 +1,189 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, PartialEq)]
+pub enum Head {
+    Branch(String),
+    Detached(String),
+    /// A branch without commits yet, right after `git init`.
+    Unborn(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RepoStatus {
+    pub head: Head,
+    pub ahead: u32,
+    pub behind: u32,
+    pub is_dirty: bool,
+}
+
+impl RepoStatus {
+    /// Asks git for the state of the work tree, without taking the index lock.
+    pub fn read(work_tree: &Path) -> io::Result<RepoStatus> {
+        let output = Command::new("git")
+            .arg("--no-optional-locks")
+            .arg("-C")
+            .arg(work_tree)
+            .args(["status", "--porcelain=v2", "--branch"])
+            .stdin(Stdio::null())
+            .output()?;
+
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(message.trim()));
+        }
+
+        Ok(RepoStatus::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// A bare repo has no work tree to compare, only its `HEAD` file tells anything.
+    pub fn read_head(git_dir: &Path) -> io::Result<RepoStatus> {
+        let head = fs::read_to_string(git_dir.join("HEAD"))?;
+        let head = head.trim();
+
+        let head = match head.strip_prefix("ref: refs/heads/") {
+            Some(branch) => Head::Branch(branch.to_string()),
+            None => Head::Detached(head.chars().take(7).collect()),
+        };
+
+        Ok(RepoStatus {
+            head,
+            ahead: 0,
+            behind: 0,
+            is_dirty: false,
+        })
+    }
+
+    pub fn parse(porcelain: &str) -> RepoStatus {
+        let mut oid = "";
+        let mut branch = "";
+        let mut ahead = 0;
+        let mut behind = 0;
+        let mut is_dirty = false;
+
+        for line in porcelain.lines() {
+            let header = match line.strip_prefix("# ") {
+                Some(header) => header,
+                None => {
+                    is_dirty |= !line.is_empty();
+                    continue;
+                }
+            };
+
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+
+            match key {
+                "branch.oid" => oid = value,
+                "branch.head" => branch = value,
+                "branch.ab" => {
+                    for count in value.split_whitespace() {
+                        if let Some(count) = count.strip_prefix('+') {
+                            ahead = count.parse().unwrap_or(0);
+                        } else if let Some(count) = count.strip_prefix('-') {
+                            behind = count.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let head = if oid == "(initial)" {
+            Head::Unborn(branch.to_string())
+        } else if branch == "(detached)" {
+            Head::Detached(oid.chars().take(7).collect())
+        } else {
+            Head::Branch(branch.to_string())
+        };
+
+        RepoStatus {
+            head,
+            ahead,
+            behind,
+            is_dirty,
+        }
+    }
+
+    /// A short summary like `main, 2 ahead, 1 behind, dirty`.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![match &self.head {
+            Head::Branch(branch) => branch.clone(),
+            Head::Detached(oid) => format!("detached at {}", oid),
+            Head::Unborn(branch) => format!("{}, no commits", branch),
+        }];
+
+        if self.ahead > 0 {
+            parts.push(format!("{} ahead", self.ahead));
+        }
+
+        if self.behind > 0 {
+            parts.push(format!("{} behind", self.behind));
+        }
+
+        if self.is_dirty {
+            parts.push(String::from("dirty"));
+        }
+
+        parts.join(", ")
+    }
+
+    /// Tags for the states that need attention, so typing `dirty` or `behind` finds them.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = vec![];
+
+        if matches!(self.head, Head::Detached(_)) {
+            tags.push(String::from("detached"));
+        }
+
+        if self.ahead > 0 {
+            tags.push(String::from("ahead"));
+        }
+
+        if self.behind > 0 {
+            tags.push(String::from("behind"));
+        }
+
+        if self.is_dirty {
+            tags.push(String::from("dirty"));
+        }
+
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_branch_with_an_upstream() {
+        let status = RepoStatus::parse(
+            "# branch.oid 1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d\n\
+             # branch.head main\n\
+             # branch.upstream origin/main\n\
+             # branch.ab +2 -1\n\
+             1 .M N... 100644 100644 100644 3b18e5 3b18e5 README.md\n\
+             ? notes.txt\n",
+        );
+
+        assert_eq!(status.head, Head::Branch(String::from("main")));
+        assert_eq!((status.ahead, status.behind), (2, 1));
+        assert!(status.is_dirty);
+        assert_eq!(status.describe(), "main, 2 ahead, 1 behind, dirty");
+        assert_eq!(status.tags(), vec!["ahead", "behind", "dirty"]);
+    }
+
+    #[test]
+    fn parses_detached_and_unborn_heads() {
+        let status = RepoStatus::parse(
+            "# branch.oid 1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d\n# branch.head (detached)\n",
+        );
+        assert_eq!(status.describe(), "detached at 1c2d3e4");
+        assert!(!status.is_dirty);
+
+        let status = RepoStatus::parse("# branch.oid (initial)\n# branch.head trunk\n");
+        assert_eq!(status.describe(), "trunk, no commits");
+    }
+}
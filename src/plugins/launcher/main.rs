@@ -1,10 +1,23 @@
+use shared::paths;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
 use std::process::Command;
 use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
 use structopt::StructOpt;
 
+const LOG_FILE: &str = "launcher.log";
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
 #[derive(Debug, StructOpt)]
 struct Options {
     command: String,
+    #[structopt(
+        parse(from_os_str),
+        help = "Passed to the command as is, without splitting"
+    )]
+    args: Vec<OsString>,
 }
 
 #[cfg(target_family = "unix")]
@@ -15,7 +28,50 @@ fn setsid() {
 #[cfg(target_family = "windows")]
 fn setsid() {}
 
+fn open_log() -> io::Result<File> {
+    let dir = paths::state_dir();
+    fs::create_dir_all(&dir)?;
+
+    let path = dir.join(LOG_FILE);
+
+    if let Ok(metadata) = fs::metadata(&path) {
+        if metadata.len() > MAX_LOG_SIZE {
+            fs::rename(&path, dir.join(format!("{}.1", LOG_FILE)))?;
+        }
+    }
+
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn log_line(log: &mut Option<File>, message: &str) {
+    if let Some(file) = log {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or(0);
+
+        let _ = writeln!(file, "[{}] {}", timestamp, message);
+    }
+}
+
+fn output_to(log: &Option<File>) -> Stdio {
+    log.as_ref()
+        .and_then(|file| file.try_clone().ok())
+        .map(Stdio::from)
+        .unwrap_or_else(Stdio::null)
+}
+
+fn exit_code(error: &io::Error) -> i32 {
+    match error.kind() {
+        io::ErrorKind::NotFound => 127,
+        io::ErrorKind::PermissionDenied => 126,
+        _ => 1,
+    }
+}
+
 fn main() {
+    // the spawned command is not a session leader, so it can't regain the terminal
+    // and gets adopted by init once the launcher exits
     setsid();
 
     let options = Options::from_args();
@@ -36,8 +92,27 @@ fn main() {
         cmd.arg(part);
     }
 
-    cmd.stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .unwrap();
+    cmd.args(&options.args);
+
+    let mut log = open_log().ok();
+    let args: Vec<_> = options.args.iter().map(|x| x.to_string_lossy()).collect();
+    log_line(
+        &mut log,
+        &format!("launching: {} {}", options.command, args.join(" ")),
+    );
+
+    let result = cmd
+        .stdin(Stdio::null())
+        .stdout(output_to(&log))
+        .stderr(output_to(&log))
+        .spawn();
+
+    match result {
+        Ok(child) => log_line(&mut log, &format!("started pid {}", child.id())),
+        Err(error) => {
+            log_line(&mut log, &format!("failed: {}", error));
+            eprintln!("{}: {}", first_part, error);
+            std::process::exit(exit_code(&error));
+        }
+    }
 }
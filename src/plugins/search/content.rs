@@ -0,0 +1,43 @@
+use crate::matcher::Matcher;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+// the same guess git and grep make: a NUL byte near the start means binary
+const BINARY_PROBE_LEN: usize = 8 * 1024;
+
+pub struct Line {
+    pub number: usize,
+    pub text: String,
+}
+
+/// The lines of a file the matcher accepts, none if the file looks binary.
+pub fn matching_lines(path: &Path, matcher: &Matcher) -> io::Result<Vec<Line>> {
+    let mut reader = BufReader::with_capacity(BINARY_PROBE_LEN, File::open(path)?);
+
+    if reader.fill_buf()?.contains(&0) {
+        return Ok(vec![]);
+    }
+
+    let mut lines = vec![];
+    let mut buffer = vec![];
+    let mut number = 0;
+
+    while reader.read_until(b'\n', &mut buffer)? > 0 {
+        number += 1;
+
+        let text = String::from_utf8_lossy(&buffer);
+        let text = text.trim_end_matches(['\n', '\r']);
+
+        if matcher.is_match(text) {
+            lines.push(Line {
+                number,
+                text: text.trim().to_string(),
+            });
+        }
+
+        buffer.clear();
+    }
+
+    Ok(lines)
+}
@@ -0,0 +1,210 @@
+use ignore::DirEntry;
+use std::fs::Metadata;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+const SIZE_UNITS: [(char, u64); 4] = [
+    ('k', 1 << 10),
+    ('m', 1 << 20),
+    ('g', 1 << 30),
+    ('t', 1 << 40),
+];
+
+const AGE_UNITS: [(char, u64); 5] = [
+    ('s', 1),
+    ('m', 60),
+    ('h', 60 * 60),
+    ('d', 24 * 60 * 60),
+    ('w', 7 * 24 * 60 * 60),
+];
+
+/// A time span like `30m`, `12h`, `2d` or `1w`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Age(pub Duration);
+
+impl FromStr for Age {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, multiplier) = split_unit(s, &AGE_UNITS)
+            .ok_or_else(|| format!("invalid age: {}, expected e.g. 30m, 12h, 2d or 1w", s))?;
+
+        let seconds = number
+            .checked_mul(multiplier)
+            .ok_or_else(|| format!("age too large: {}", s))?;
+
+        Ok(Age(Duration::from_secs(seconds)))
+    }
+}
+
+/// A size in bytes like `512`, `10k`, `5M` or `1G`, in powers of 1024.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Size(pub u64);
+
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(bytes) = s.parse() {
+            return Ok(Size(bytes));
+        }
+
+        let (number, multiplier) = split_unit(s, &SIZE_UNITS)
+            .ok_or_else(|| format!("invalid size: {}, expected e.g. 512, 10k, 5M or 1G", s))?;
+
+        let bytes = number
+            .checked_mul(multiplier)
+            .ok_or_else(|| format!("size too large: {}", s))?;
+
+        Ok(Size(bytes))
+    }
+}
+
+fn split_unit(s: &str, units: &[(char, u64)]) -> Option<(u64, u64)> {
+    let (index, unit) = s.char_indices().last()?;
+    let number = s[..index].parse().ok()?;
+    let (_, multiplier) = units
+        .iter()
+        .find(|(x, _)| *x == unit.to_ascii_lowercase())?;
+
+    Some((number, *multiplier))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileType {
+    File,
+    Directory,
+    Symlink,
+    Executable,
+}
+
+impl FromStr for FileType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f" => Ok(FileType::File),
+            "d" => Ok(FileType::Directory),
+            "l" => Ok(FileType::Symlink),
+            "x" => Ok(FileType::Executable),
+            _ => Err(format!("unknown type: {}, expected f, d, l or x", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Mtime,
+    Size,
+    Name,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mtime" => Ok(SortKey::Mtime),
+            "size" => Ok(SortKey::Size),
+            "name" => Ok(SortKey::Name),
+            _ => Err(format!("unknown sort: {}, expected mtime, size or name", s)),
+        }
+    }
+}
+
+pub struct Filters {
+    pub newer: Option<Age>,
+    pub older: Option<Age>,
+    pub min_size: Option<Size>,
+    pub max_size: Option<Size>,
+    pub file_type: Option<FileType>,
+}
+
+impl Filters {
+    pub fn is_match(&self, entry: &DirEntry, metadata: &Metadata, now: SystemTime) -> bool {
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let age = now.duration_since(modified).unwrap_or_default();
+
+        if matches!(self.newer, Some(Age(newer)) if age > newer) {
+            return false;
+        }
+
+        if matches!(self.older, Some(Age(older)) if age < older) {
+            return false;
+        }
+
+        if matches!(self.min_size, Some(Size(min)) if metadata.len() < min) {
+            return false;
+        }
+
+        if matches!(self.max_size, Some(Size(max)) if metadata.len() > max) {
+            return false;
+        }
+
+        match self.file_type {
+            Some(file_type) => is_type(entry, metadata, file_type),
+            None => true,
+        }
+    }
+}
+
+// the walk doesn't follow links, so the metadata is the link's own
+fn is_type(entry: &DirEntry, metadata: &Metadata, file_type: FileType) -> bool {
+    match file_type {
+        FileType::File => metadata.is_file(),
+        FileType::Directory => metadata.is_dir(),
+        FileType::Symlink => entry.path_is_symlink(),
+        FileType::Executable => metadata.is_file() && is_executable(entry, metadata),
+    }
+}
+
+#[cfg(target_family = "unix")]
+fn is_executable(_entry: &DirEntry, metadata: &Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(target_family = "windows")]
+fn is_executable(entry: &DirEntry, _metadata: &Metadata) -> bool {
+    let extension = entry.path().extension().map(|x| x.to_ascii_lowercase());
+
+    matches!(
+        extension.as_ref().and_then(|x| x.to_str()),
+        Some("exe" | "bat" | "cmd" | "com")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!("45s".parse(), Ok(Age(Duration::from_secs(45))));
+        assert_eq!("2d".parse(), Ok(Age(Duration::from_secs(2 * 24 * 60 * 60))));
+        assert_eq!("1W".parse(), Ok(Age(Duration::from_secs(7 * 24 * 60 * 60))));
+        assert!("2".parse::<Age>().is_err());
+        assert!("d".parse::<Age>().is_err());
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!("512".parse(), Ok(Size(512)));
+        assert_eq!("10k".parse(), Ok(Size(10 * 1024)));
+        assert_eq!("5M".parse(), Ok(Size(5 * 1024 * 1024)));
+        assert!("5x".parse::<Size>().is_err());
+    }
+
+    #[test]
+    fn rejects_values_that_overflow() {
+        assert_eq!(
+            "999999999999999w".parse::<Age>(),
+            Err(String::from("age too large: 999999999999999w"))
+        );
+        assert_eq!(
+            "99999999999t".parse::<Size>(),
+            Err(String::from("size too large: 99999999999t"))
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LabelStyle {
+    Relative,
+    Name,
+    Absolute,
+    ShortestUnique,
+}
+
+impl FromStr for LabelStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relative" => Ok(LabelStyle::Relative),
+            "name" => Ok(LabelStyle::Name),
+            "absolute" => Ok(LabelStyle::Absolute),
+            "shortest-unique" => Ok(LabelStyle::ShortestUnique),
+            _ => Err(format!(
+                "unknown label: {}, expected relative, name, absolute or shortest-unique",
+                s
+            )),
+        }
+    }
+}
+
+/// Labels for the paths, in the same order.
+pub fn create_labels(paths: &[&Path], working_dir: &Path, style: LabelStyle) -> Vec<String> {
+    match style {
+        LabelStyle::Relative => paths
+            .iter()
+            .map(|x| relative(x, working_dir).to_string_lossy().to_string())
+            .collect(),
+        LabelStyle::Name => paths.iter().map(|x| name(x)).collect(),
+        LabelStyle::Absolute => paths
+            .iter()
+            .map(|x| x.to_string_lossy().to_string())
+            .collect(),
+        LabelStyle::ShortestUnique => {
+            let relative_paths: Vec<&Path> =
+                paths.iter().map(|x| relative(x, working_dir)).collect();
+            shortest_unique(&relative_paths)
+        }
+    }
+}
+
+fn name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => path.to_string_lossy().to_string(),
+    }
+}
+
+// the working directory itself has no relative path, it keeps the whole one
+fn relative<'a>(path: &'a Path, working_dir: &Path) -> &'a Path {
+    match path.strip_prefix(working_dir) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative,
+        _ => path,
+    }
+}
+
+// starts from the names and adds a parent to every label that is shared by different paths,
+// until none is or those paths run out of parents; the same path may appear more than once
+fn shortest_unique(paths: &[&Path]) -> Vec<String> {
+    let components: Vec<Vec<Component>> = paths.iter().map(|x| x.components().collect()).collect();
+    let mut lengths = vec![1; paths.len()];
+
+    loop {
+        let labels: Vec<PathBuf> = components
+            .iter()
+            .zip(lengths.iter())
+            .map(|(parts, length)| {
+                parts[parts.len().saturating_sub(*length)..]
+                    .iter()
+                    .collect()
+            })
+            .collect();
+
+        let mut owners: HashMap<&PathBuf, BTreeSet<&Path>> = HashMap::new();
+
+        for (label, path) in labels.iter().zip(paths.iter()) {
+            owners.entry(label).or_default().insert(path);
+        }
+
+        let mut is_changed = false;
+
+        for (index, label) in labels.iter().enumerate() {
+            if owners[label].len() > 1 && lengths[index] < components[index].len() {
+                lengths[index] += 1;
+                is_changed = true;
+            }
+        }
+
+        if !is_changed {
+            return labels
+                .iter()
+                .map(|x| x.to_string_lossy().to_string())
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(paths: &[&str], style: LabelStyle) -> Vec<String> {
+        let paths: Vec<PathBuf> = paths.iter().map(|x| Path::new("/work").join(x)).collect();
+        let paths: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+
+        create_labels(&paths, Path::new("/work"), style)
+    }
+
+    #[test]
+    fn adds_parents_until_labels_are_unique() {
+        let paths = [
+            "src/app/mod.rs",
+            "src/shared/mod.rs",
+            "src/plugins/search/main.rs",
+            "src/plugins/git-repo/main.rs",
+            "src/main.rs",
+            "src/app/model.rs",
+        ];
+
+        assert_eq!(
+            labels(&paths, LabelStyle::ShortestUnique),
+            vec![
+                "app/mod.rs",
+                "shared/mod.rs",
+                "search/main.rs",
+                "git-repo/main.rs",
+                "src/main.rs",
+                "model.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_the_label_of_a_repeated_path() {
+        let paths = ["src/main.rs", "src/main.rs", "docs/main.rs"];
+
+        assert_eq!(
+            labels(&paths, LabelStyle::ShortestUnique),
+            vec!["src/main.rs", "src/main.rs", "docs/main.rs"]
+        );
+    }
+
+    #[test]
+    fn labels_with_the_other_styles() {
+        let paths = ["src/main.rs"];
+
+        assert_eq!(labels(&paths, LabelStyle::Name), vec!["main.rs"]);
+        assert_eq!(labels(&paths, LabelStyle::Relative), vec!["src/main.rs"]);
+        assert_eq!(
+            labels(&paths, LabelStyle::Absolute),
+            vec!["/work/src/main.rs"]
+        );
+    }
+}
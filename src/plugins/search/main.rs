@@ -1,10 +1,25 @@
-use shared::plugin::{JustListAction, JustListPlugin};
+use content::Line;
+use filters::{Age, FileType, Filters, Size, SortKey};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::WalkBuilder;
+use labels::LabelStyle;
+use matcher::Matcher;
+use shared::plugin::{self, Describe, JustListAction, JustListPlugin, PluginDescription};
 use shared::serialization::*;
+use shared::walk;
+use std::cmp::Reverse;
 use std::env;
+use std::io;
+use std::mem;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 use structopt::StructOpt;
-use walkdir::WalkDir;
+
+pub mod content;
+pub mod filters;
+pub mod labels;
+pub mod matcher;
 
 #[derive(Debug)]
 struct QueryFlags {
@@ -35,11 +50,76 @@ fn parse_query_flags(s: &str) -> QueryFlags {
 }
 
 #[derive(Debug, StructOpt)]
+#[structopt(about = "This plugin searches for files by name, directory, extension or content")]
 struct Options {
     query: String,
     command_template: String,
-    #[structopt(short, parse(from_str = parse_query_flags), help="n - for names, d - for directories, e - for extensions")]
+    #[structopt(short, default_value = "", parse(from_str = parse_query_flags), help="n - for names, d - for directories, e - for extensions; names if empty, unused with --content")]
     query_flags: QueryFlags,
+    #[structopt(
+        long = "query",
+        number_of_values = 1,
+        help = "Another query matched in the same run, can be repeated"
+    )]
+    queries: Vec<String>,
+    #[structopt(
+        long,
+        conflicts_with_all = &["regex", "content"],
+        help = "Treat queries as globs, matched against the name and the relative path"
+    )]
+    glob: bool,
+    #[structopt(
+        long,
+        help = "Treat queries as regular expressions, matched against the name and the relative path"
+    )]
+    regex: bool,
+    #[structopt(
+        long,
+        help = "Search the lines of text files instead of names, one item per matching line"
+    )]
+    content: bool,
+    #[structopt(long, help = "Include hidden files and directories")]
+    hidden: bool,
+    #[structopt(
+        long,
+        help = "Don't read .gitignore, .ignore and the global git excludes, and walk into .git"
+    )]
+    no_ignore: bool,
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "Skip entries matching a gitignore style glob, can be repeated"
+    )]
+    exclude: Vec<String>,
+    #[structopt(
+        long,
+        help = "Only entries modified within this age, e.g. 30m, 12h, 2d or 1w"
+    )]
+    newer: Option<Age>,
+    #[structopt(long, help = "Only entries modified longer ago than this age")]
+    older: Option<Age>,
+    #[structopt(
+        long,
+        help = "Only entries of at least this size, e.g. 512, 10k, 5M or 1G"
+    )]
+    min_size: Option<Size>,
+    #[structopt(long, help = "Only entries of at most this size")]
+    max_size: Option<Size>,
+    #[structopt(
+        long = "type",
+        help = "f - for files, d - for directories, l - for symlinks, x - for executables"
+    )]
+    file_type: Option<FileType>,
+    #[structopt(
+        long,
+        help = "mtime - newest first, size - largest first, name; sorted by path if omitted"
+    )]
+    sort: Option<SortKey>,
+    #[structopt(
+        long = "label",
+        help = "relative, name, absolute or shortest-unique path; name by default, relative with --content"
+    )]
+    label_style: Option<LabelStyle>,
     #[structopt(long, short)]
     verbose: bool,
     #[structopt(long, short, default_value = "1")]
@@ -54,18 +134,183 @@ struct Options {
         help = "This flag should indicate, that the app runs in a terminal"
     )]
     is_terminal: bool,
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "Seconds to reuse the last result for, 0 disables the cache"
+    )]
+    cache: u64,
+}
+
+impl Describe for Options {
+    fn describe() -> PluginDescription {
+        PluginDescription::new(
+            "search",
+            "This plugin searches for files by name, directory, extension or content",
+        )
+        .argument("query", "")
+        .argument("command-template", "")
+        .short_option(
+            'q',
+            "query-flags",
+            "n - for names, d - for directories, e - for extensions; names if empty, unused with --content",
+        )
+        .option("query", "Another query matched in the same run, can be repeated")
+        .flag(
+            "glob",
+            "Treat queries as globs, matched against the name and the relative path",
+        )
+        .flag(
+            "regex",
+            "Treat queries as regular expressions, matched against the name and the relative path",
+        )
+        .flag(
+            "content",
+            "Search the lines of text files instead of names, one item per matching line",
+        )
+        .flag("hidden", "Include hidden files and directories")
+        .flag(
+            "no-ignore",
+            "Don't read .gitignore, .ignore and the global git excludes, and walk into .git",
+        )
+        .option(
+            "exclude",
+            "Skip entries matching a gitignore style glob, can be repeated",
+        )
+        .option(
+            "newer",
+            "Only entries modified within this age, e.g. 30m, 12h, 2d or 1w",
+        )
+        .option("older", "Only entries modified longer ago than this age")
+        .option(
+            "min-size",
+            "Only entries of at least this size, e.g. 512, 10k, 5M or 1G",
+        )
+        .option("max-size", "Only entries of at most this size")
+        .option(
+            "type",
+            "f - for files, d - for directories, l - for symlinks, x - for executables",
+        )
+        .option(
+            "sort",
+            "mtime - newest first, size - largest first, name; sorted by path if omitted",
+        )
+        .option(
+            "label",
+            "relative, name, absolute or shortest-unique path; name by default, relative with --content",
+        )
+        .flag("verbose", "")
+        .short('v')
+        .option("depth", "")
+        .short('d')
+        .option("working-dir", "")
+        .short('w')
+        .option("title", "")
+        .flag(
+            "terminal",
+            "This flag should indicate, that the app runs in a terminal",
+        )
+        .short('t')
+        .option(
+            "cache",
+            "Seconds to reuse the last result for, 0 disables the cache",
+        )
+    }
 }
 
 impl Options {
+    pub fn create_matcher(&self) -> io::Result<Matcher> {
+        let mut queries = vec![self.query.clone()];
+        queries.extend(self.queries.iter().cloned());
+
+        if self.glob {
+            Matcher::glob(&queries)
+        } else if self.regex {
+            Matcher::regex(&queries)
+        } else {
+            Ok(Matcher::contains(&queries))
+        }
+    }
+
+    pub fn create_excludes(&self, working_dir: &Path) -> io::Result<Override> {
+        let mut builder = OverrideBuilder::new(working_dir);
+
+        for exclude in self.exclude.iter() {
+            builder
+                .add(&format!("!{}", exclude))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        }
+
+        builder
+            .build()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    pub fn filters(&self) -> Filters {
+        Filters {
+            newer: self.newer,
+            older: self.older,
+            min_size: self.min_size,
+            max_size: self.max_size,
+            file_type: self.file_type,
+        }
+    }
+
+    pub fn label_style(&self) -> LabelStyle {
+        match self.label_style {
+            Some(label_style) => label_style,
+            None if self.content => LabelStyle::Relative,
+            None => LabelStyle::Name,
+        }
+    }
+
     pub fn get_working_dir_or<'b, 'a: 'b>(&'a self, default: &'b PathBuf) -> &'b PathBuf {
         self.working_dir.as_ref().unwrap_or(default)
     }
 }
 
-struct Search {}
+struct Search {
+    matcher: Matcher,
+    excludes: Override,
+}
+
+struct Found {
+    item: SelectableItem,
+    path: PathBuf,
+    line: Option<Line>,
+    name: String,
+    modified: SystemTime,
+    size: u64,
+}
 
 impl JustListAction<Options> for Search {
     fn execute(&self, groups: &mut Groups, options: &Options) {
+        self.search(groups, options, None);
+    }
+
+    fn execute_streamed(
+        &self,
+        groups: &mut Groups,
+        options: &Options,
+        batch: &mut dyn FnMut(Groups),
+    ) {
+        // a sort and shortest unique labels need every path
+        if options.sort.is_some() || options.label_style() == LabelStyle::ShortestUnique {
+            return self.execute(groups, options);
+        }
+
+        self.search(groups, options, Some(batch));
+    }
+}
+
+impl Search {
+    // hands the items found so far to `batch` every now and then, if there is one
+    fn search(
+        &self,
+        groups: &mut Groups,
+        options: &Options,
+        mut batch: Option<&mut dyn FnMut(Groups)>,
+    ) {
         let curr_dir = env::current_dir().unwrap();
         let working_dir = options.get_working_dir_or(&curr_dir);
         let depth = options.depth;
@@ -76,67 +321,622 @@ impl JustListAction<Options> for Search {
             "files"
         };
 
-        let mut group = ListGroup {
+        let create_group = |items| ListGroup {
             label: title.to_string(),
-            items: vec![],
+            items,
             command_template: Some(options.command_template.to_string()),
             is_terminal: Some(options.is_terminal),
+            actions: vec![],
         };
 
-        for item in WalkDir::new(&working_dir).max_depth(depth as usize) {
-            if let Err(_error) = item {
-                if options.verbose {
-                    eprintln!("{}", _error);
+        let respect_ignore = !options.no_ignore;
+
+        // .gitignore applies outside of a repository too, a plain directory tree is worth filtering
+        let walker = WalkBuilder::new(working_dir)
+            .max_depth(Some(depth as usize))
+            .hidden(!options.hidden)
+            .parents(respect_ignore)
+            .ignore(respect_ignore)
+            .git_ignore(respect_ignore)
+            .git_global(respect_ignore)
+            .git_exclude(respect_ignore)
+            .require_git(false)
+            .overrides(self.excludes.clone())
+            .filter_entry(move |entry| !respect_ignore || entry.file_name() != ".git")
+            .build_parallel();
+
+        let filters = options.filters();
+        let now = SystemTime::now();
+        let mut found = vec![];
+        let mut batches = plugin::Batches::new();
+
+        walk::walk_parallel(
+            walker,
+            options.verbose,
+            |entry| {
+                let path = entry.path();
+
+                if !options.content
+                    && !is_match(&self.matcher, path, working_dir, &options.query_flags)
+                {
+                    return None;
                 }
-                continue;
-            }
 
-            let dir_item = item.unwrap();
-            let path = dir_item.path();
+                let metadata = entry.metadata().ok()?;
 
-            if is_match(&options.query, path, &options.query_flags) {
-                let file_name = path.file_name().unwrap();
-                let file_path = path.to_str().unwrap();
+                if !filters.is_match(entry, &metadata, now) {
+                    return None;
+                }
 
-                group.items.push(SelectableItem {
-                    label: String::from(file_name.to_str().unwrap()),
-                    param: String::from(file_path),
-                });
-            }
-        }
+                let name = path.file_name()?.to_string_lossy().to_string();
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                let size = metadata.len();
+
+                let found = |line: Option<Line>| Found {
+                    item: SelectableItem {
+                        description: relative_dir(path, working_dir).filter(|_| line.is_none()),
+                        values: line
+                            .iter()
+                            .map(|x| (String::from("line"), x.number.to_string()))
+                            .collect(),
+                        ..SelectableItem::for_path(String::new(), path)
+                    },
+                    path: path.to_path_buf(),
+                    line,
+                    name: name.clone(),
+                    modified,
+                    size,
+                };
+
+                if !options.content {
+                    return Some(vec![found(None)]);
+                }
+
+                if !metadata.is_file() {
+                    return None;
+                }
+
+                match content::matching_lines(path, &self.matcher) {
+                    Ok(lines) => Some(lines.into_iter().map(|x| found(Some(x))).collect()),
+                    Err(error) => {
+                        if options.verbose {
+                            eprintln!("{}: {}", path.display(), error);
+                        }
+                        None
+                    }
+                }
+            },
+            |x| {
+                found.extend(x);
+
+                if let Some(batch) = batch.as_mut() {
+                    if batches.is_due() {
+                        let items = create_items(mem::take(&mut found), working_dir, options);
+                        batches.send(groups, create_group(items), *batch);
+                    }
+                }
+            },
+        );
+
+        let items = create_items(found, working_dir, options);
+        groups.groups.push(create_group(items));
+    }
+}
+
+fn create_items(
+    mut found: Vec<Found>,
+    working_dir: &Path,
+    options: &Options,
+) -> Vec<SelectableItem> {
+    // the walk finishes in any order, keep the output stable for the cache and the eye,
+    // the sorts are stable, so the lines of a file stay in order
+    found.sort_by(|a, b| a.item.param.cmp(&b.item.param));
 
-        groups.groups.push(group);
+    match options.sort {
+        Some(SortKey::Mtime) => found.sort_by_key(|x| Reverse(x.modified)),
+        Some(SortKey::Size) => found.sort_by_key(|x| Reverse(x.size)),
+        Some(SortKey::Name) => found.sort_by(|a, b| a.name.cmp(&b.name)),
+        None => {}
     }
+
+    let paths: Vec<&Path> = found.iter().map(|x| x.path.as_path()).collect();
+    let labels = labels::create_labels(&paths, working_dir, options.label_style());
+
+    found
+        .into_iter()
+        .zip(labels)
+        .map(|(found, label)| SelectableItem {
+            label: match found.line {
+                Some(line) => format!("{}:{}: {}", label, line.number, line.text),
+                None => label,
+            },
+            ..found.item
+        })
+        .collect()
+}
+
+fn relative_dir(path: &Path, working_dir: &Path) -> Option<String> {
+    let parent = path.parent()?.strip_prefix(working_dir).ok()?;
+
+    if parent.as_os_str().is_empty() {
+        return None;
+    }
+
+    Some(parent.to_string_lossy().to_string())
 }
 
-fn is_match(query: &str, path: &Path, query_flags: &QueryFlags) -> bool {
+fn is_match(matcher: &Matcher, path: &Path, working_dir: &Path, query_flags: &QueryFlags) -> bool {
     let is_dir = path.is_dir();
     let mut result = false;
-    let file_name = path.file_name().unwrap().to_str().unwrap();
+
+    let file_name = match path.file_name() {
+        Some(file_name) => file_name.to_string_lossy(),
+        None => return false,
+    };
+
+    let relative_path = path.strip_prefix(working_dir).unwrap_or(path);
 
     if is_dir && query_flags.directories {
-        result |= file_name.contains(query);
+        result |= matcher.is_match(&file_name) || matcher.is_path_match(relative_path);
     }
 
     if query_flags.extensions && !is_dir {
-        if let Some(extension_wrap) = path.extension() {
-            let extension = extension_wrap.to_str().unwrap();
-            result |= extension.contains(query);
+        if let Some(extension) = path.extension() {
+            result |= matcher.is_match(&extension.to_string_lossy());
         }
     }
 
     if query_flags.names && !is_dir {
-        result |= file_name.contains(query);
+        result |= matcher.is_match(&file_name) || matcher.is_path_match(relative_path);
     }
 
     result
 }
 
 fn main() -> std::io::Result<()> {
-    let options = Options::from_args();
+    let options: Options = plugin::parse_options()?;
+    let matcher = options.create_matcher()?;
+    let curr_dir = env::current_dir()?;
+    let excludes = options.create_excludes(options.get_working_dir_or(&curr_dir))?;
 
-    let plugin = JustListPlugin::new(options);
+    let cache_ttl = Duration::from_secs(options.cache);
+    let plugin = JustListPlugin::new(options).cache_for(cache_ttl);
 
-    let action = Search {};
+    let action = Search { matcher, excludes };
     plugin.main(&action)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::plugin::testing::{self, TempDir};
+
+    fn project() -> TempDir {
+        TempDir::builder()
+            .file("Cargo.toml", "")
+            .file("src/main.rs", "")
+            .file("src/config.toml", "")
+            .file("docs/readme.md", "")
+            .dir("src/tomls")
+            .build()
+    }
+
+    fn search(dir: &TempDir, args: &[&str]) -> Groups {
+        let working_dir = dir.path().to_str().unwrap();
+        let mut argv = vec!["search"];
+        argv.extend_from_slice(args);
+        argv.extend_from_slice(&["cmd", "-w", working_dir, "-d", "3"]);
+
+        let options = Options::from_iter(argv);
+        let action = Search {
+            matcher: options.create_matcher().unwrap(),
+            excludes: options.create_excludes(dir.path()).unwrap(),
+        };
+
+        let plugin = JustListPlugin::new(options);
+        testing::run(&plugin, &action, testing::upstream_groups())
+    }
+
+    #[test]
+    fn appends_its_group_to_the_input() {
+        let dir = project();
+        let groups = search(&dir, &["main", "-q", "n"]);
+
+        assert_eq!(groups.groups.len(), 2);
+        assert_eq!(groups.groups[0].label, "upstream");
+        assert_eq!(groups.groups[1].label, "files");
+        assert_eq!(groups.groups[1].command_template.as_deref(), Some("cmd"));
+    }
+
+    #[test]
+    fn matches_names() {
+        let dir = project();
+        let groups = search(&dir, &["main", "-q", "n"]);
+        let group = &groups.groups[1];
+
+        assert_eq!(testing::labels(group), vec!["main.rs"]);
+        assert_eq!(group.items[0].description.as_deref(), Some("src"));
+        assert!(group.items[0].param.ends_with("main.rs"));
+    }
+
+    #[test]
+    fn matches_extensions() {
+        let dir = project();
+        let groups = search(&dir, &["toml", "-q", "e"]);
+
+        assert_eq!(
+            testing::labels(&groups.groups[1]),
+            vec!["Cargo.toml", "config.toml"]
+        );
+    }
+
+    #[test]
+    fn matches_directories() {
+        let dir = project();
+        let groups = search(&dir, &["toml", "-q", "d"]);
+
+        assert_eq!(testing::labels(&groups.groups[1]), vec!["tomls"]);
+    }
+
+    #[test]
+    fn respects_the_depth() {
+        let dir = project();
+        let working_dir = dir.path().to_str().unwrap();
+        let options = Options::from_iter(vec![
+            "search",
+            "toml",
+            "cmd",
+            "-q",
+            "e",
+            "-w",
+            working_dir,
+            "-d",
+            "1",
+        ]);
+        let action = Search {
+            matcher: options.create_matcher().unwrap(),
+            excludes: options.create_excludes(dir.path()).unwrap(),
+        };
+        let plugin = JustListPlugin::new(options);
+        let groups = testing::run(&plugin, &action, Groups::new());
+
+        assert_eq!(testing::labels(&groups.groups[0]), vec!["Cargo.toml"]);
+    }
+
+    #[test]
+    fn matches_several_queries() {
+        let dir = project();
+        let groups = search(&dir, &["main", "--query", "readme", "-q", "n"]);
+
+        assert_eq!(
+            testing::labels(&groups.groups[1]),
+            vec!["main.rs", "readme.md"]
+        );
+    }
+
+    #[test]
+    fn matches_globs_against_names_and_relative_paths() {
+        let dir = project();
+
+        let groups = search(&dir, &["*.toml", "--glob", "-q", "n"]);
+        assert_eq!(
+            testing::labels(&groups.groups[1]),
+            vec!["Cargo.toml", "config.toml"]
+        );
+
+        let groups = search(&dir, &["src/*", "--glob", "-q", "n"]);
+        assert_eq!(
+            testing::labels(&groups.groups[1]),
+            vec!["config.toml", "main.rs"]
+        );
+
+        let groups = search(&dir, &["**/docs/*.md", "--glob", "-q", "n"]);
+        assert_eq!(testing::labels(&groups.groups[1]), vec!["readme.md"]);
+    }
+
+    #[test]
+    fn matches_regexes_against_names_and_relative_paths() {
+        let dir = project();
+
+        let groups = search(&dir, &["^c.*\\.toml$", "--regex", "-q", "n"]);
+        assert_eq!(testing::labels(&groups.groups[1]), vec!["config.toml"]);
+
+        let groups = search(&dir, &["^docs/", "--regex", "--query", "^main", "-q", "n"]);
+        assert_eq!(
+            testing::labels(&groups.groups[1]),
+            vec!["main.rs", "readme.md"]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let options = Options::from_iter(vec!["search", "(", "cmd", "-q", "n", "--regex"]);
+        let error = options.create_matcher().err().unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    fn ignored_project() -> TempDir {
+        TempDir::builder()
+            .file(".gitignore", "target/\n*.log\n")
+            .file("app.rs", "")
+            .file("debug.log", "")
+            .file("target/build.rs", "")
+            .file("docs/.ignore", "draft.rs\n")
+            .file("docs/draft.rs", "")
+            .file(".config/settings.rs", "")
+            .file(".git/hooks.rs", "")
+            .file("vendor/lib.rs", "")
+            .build()
+    }
+
+    #[test]
+    fn respects_ignore_files_and_skips_hidden_entries() {
+        let dir = ignored_project();
+        let groups = search(&dir, &["rs", "-q", "e"]);
+
+        assert_eq!(testing::labels(&groups.groups[1]), vec!["app.rs", "lib.rs"]);
+
+        let groups = search(&dir, &["log", "-q", "e"]);
+        assert!(groups.groups[1].items.is_empty());
+    }
+
+    #[test]
+    fn includes_hidden_entries_on_request() {
+        let dir = ignored_project();
+        let groups = search(&dir, &["rs", "-q", "e", "--hidden"]);
+
+        assert_eq!(
+            testing::labels(&groups.groups[1]),
+            vec!["app.rs", "lib.rs", "settings.rs"]
+        );
+    }
+
+    #[test]
+    fn skips_excluded_globs() {
+        let dir = ignored_project();
+        let groups = search(&dir, &["rs", "-q", "e", "--exclude", "vendor/"]);
+
+        assert_eq!(testing::labels(&groups.groups[1]), vec!["app.rs"]);
+    }
+
+    #[test]
+    fn walks_everything_without_ignore_files() {
+        let dir = ignored_project();
+        let groups = search(&dir, &["rs", "-q", "e", "--no-ignore", "--hidden"]);
+
+        assert_eq!(
+            testing::labels(&groups.groups[1]),
+            vec![
+                "app.rs",
+                "build.rs",
+                "draft.rs",
+                "hooks.rs",
+                "lib.rs",
+                "settings.rs"
+            ]
+        );
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn keeps_the_exact_bytes_of_non_utf8_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = TempDir::builder().build();
+        let path = dir.path().join(OsStr::from_bytes(b"caf\xe9.txt"));
+
+        // some filesystems refuse names that aren't valid UTF-8
+        if std::fs::write(&path, "").is_err() {
+            return;
+        }
+
+        let groups = search(&dir, &["caf", "-q", "n"]);
+        let item = &groups.groups[1].items[0];
+
+        assert_eq!(item.label, "caf\u{fffd}.txt");
+        assert_eq!(item.launch_param(), path.into_os_string());
+    }
+
+    fn dated_project() -> TempDir {
+        let dir = TempDir::builder()
+            .file("new.log", "1234567890")
+            .file("old.log", "12345")
+            .file("ancient.log", "")
+            .file("logs/today.log", "1")
+            .build();
+
+        let day = Duration::from_secs(24 * 60 * 60);
+        set_age(&dir.path().join("old.log"), day * 3);
+        set_age(&dir.path().join("ancient.log"), day * 30);
+
+        dir
+    }
+
+    fn set_age(path: &Path, age: Duration) {
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    fn item_labels(groups: &Groups) -> Vec<&str> {
+        groups.groups[1]
+            .items
+            .iter()
+            .map(|x| x.label.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn filters_by_age() {
+        let dir = dated_project();
+
+        let groups = search(&dir, &["log", "-q", "e", "--newer", "2d"]);
+        assert_eq!(item_labels(&groups), vec!["today.log", "new.log"]);
+
+        let groups = search(&dir, &["log", "-q", "e", "--older", "2d", "--newer", "1w"]);
+        assert_eq!(item_labels(&groups), vec!["old.log"]);
+    }
+
+    #[test]
+    fn filters_by_size() {
+        let dir = dated_project();
+
+        let groups = search(
+            &dir,
+            &["log", "-q", "e", "--min-size", "5", "--max-size", "1k"],
+        );
+        assert_eq!(
+            testing::labels(&groups.groups[1]),
+            vec!["new.log", "old.log"]
+        );
+    }
+
+    #[test]
+    fn filters_by_type() {
+        let dir = dated_project();
+
+        let groups = search(&dir, &["log", "-q", "nd", "--type", "d"]);
+        assert_eq!(item_labels(&groups), vec!["logs"]);
+
+        let groups = search(&dir, &["log", "-q", "nd", "--type", "f"]);
+        assert_eq!(groups.groups[1].items.len(), 4);
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn filters_executables_and_symlinks() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = TempDir::builder()
+            .file("run.sh", "")
+            .file("notes.sh", "")
+            .build();
+
+        let script = dir.path().join("run.sh");
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        symlink(&script, dir.path().join("link.sh")).unwrap();
+
+        let groups = search(&dir, &["sh", "-q", "e", "--type", "x"]);
+        assert_eq!(item_labels(&groups), vec!["run.sh"]);
+
+        let groups = search(&dir, &["sh", "-q", "e", "--type", "l"]);
+        assert_eq!(item_labels(&groups), vec!["link.sh"]);
+    }
+
+    #[test]
+    fn sorts_by_metadata() {
+        let dir = dated_project();
+
+        let groups = search(&dir, &["log", "-q", "e", "--sort", "size"]);
+        assert_eq!(
+            item_labels(&groups),
+            vec!["new.log", "old.log", "today.log", "ancient.log"]
+        );
+
+        let groups = search(&dir, &["log", "-q", "e", "--sort", "mtime"]);
+        assert_eq!(item_labels(&groups)[2..], ["old.log", "ancient.log"]);
+
+        let groups = search(&dir, &["log", "-q", "e", "--sort", "name"]);
+        assert_eq!(
+            item_labels(&groups),
+            vec!["ancient.log", "new.log", "old.log", "today.log"]
+        );
+    }
+
+    fn text_project() -> TempDir {
+        TempDir::builder()
+            .file("notes.md", "todo: write docs\ndone\n  TODO: tests\n")
+            .file("src/main.rs", "fn main() {\n    // todo: args\n}\n")
+            .file("image.png", "todo\0binary")
+            .build()
+    }
+
+    #[test]
+    fn matches_lines_of_text_files() {
+        let dir = text_project();
+        let groups = search(&dir, &["todo", "--content"]);
+        let group = &groups.groups[1];
+
+        assert_eq!(
+            item_labels(&groups),
+            vec![
+                "notes.md:1: todo: write docs",
+                "src/main.rs:2: // todo: args"
+            ]
+        );
+        assert!(group.items[1].param.ends_with("main.rs"));
+        assert_eq!(
+            group.items[1].values.get("line").map(String::as_str),
+            Some("2")
+        );
+    }
+
+    #[test]
+    fn matches_lines_against_regexes() {
+        let dir = text_project();
+        let groups = search(&dir, &["(?i)^\\s*todo", "--content", "--regex"]);
+
+        assert_eq!(
+            item_labels(&groups),
+            vec!["notes.md:1: todo: write docs", "notes.md:3: TODO: tests"]
+        );
+    }
+
+    #[test]
+    fn labels_paths_by_the_requested_style() {
+        let dir = TempDir::builder()
+            .file("app/mod.rs", "")
+            .file("shared/mod.rs", "")
+            .file("main.rs", "")
+            .build();
+
+        let groups = search(&dir, &["rs", "-q", "e", "--label", "shortest-unique"]);
+        assert_eq!(
+            item_labels(&groups),
+            vec!["app/mod.rs", "main.rs", "shared/mod.rs"]
+        );
+
+        let groups = search(&dir, &["mod", "-q", "n", "--label", "relative"]);
+        assert_eq!(item_labels(&groups), vec!["app/mod.rs", "shared/mod.rs"]);
+    }
+
+    #[test]
+    fn streams_the_same_items_after_the_input() {
+        let dir = project();
+        let working_dir = dir.path().to_str().unwrap();
+        let options = Options::from_iter(vec![
+            "search",
+            "toml",
+            "cmd",
+            "-q",
+            "e",
+            "-w",
+            working_dir,
+            "-d",
+            "3",
+        ]);
+        let action = Search {
+            matcher: options.create_matcher().unwrap(),
+            excludes: options.create_excludes(dir.path()).unwrap(),
+        };
+        let plugin = JustListPlugin::new(options).streamed(true);
+        let documents = testing::run_streamed(&plugin, &action, testing::upstream_groups());
+
+        let groups: Vec<ListGroup> = documents.into_iter().flat_map(|x| x.groups).collect();
+        let mut labels: Vec<&str> = groups
+            .iter()
+            .filter(|x| x.label == "files")
+            .flat_map(|x| x.items.iter().map(|x| x.label.as_str()))
+            .collect();
+        labels.sort_unstable();
+
+        assert_eq!(groups[0].label, "upstream");
+        assert_eq!(labels, vec!["Cargo.toml", "config.toml"]);
+    }
+
+    #[test]
+    fn describes_every_option() {
+        testing::assert_described::<Options>();
+    }
+}
@@ -0,0 +1,60 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::RegexSet;
+use std::io;
+use std::path::Path;
+
+/// Matches the queries of a run against names, relative paths and extensions.
+pub enum Matcher {
+    Contains(Vec<String>),
+    Glob(GlobSet),
+    Regex(RegexSet),
+}
+
+impl Matcher {
+    pub fn contains(queries: &[String]) -> Matcher {
+        Matcher::Contains(queries.to_vec())
+    }
+
+    // `*` stops at a separator, so `*.rs` only matches the relative path of top level files
+    pub fn glob(queries: &[String]) -> io::Result<Matcher> {
+        let mut builder = GlobSetBuilder::new();
+
+        for query in queries.iter() {
+            let glob = GlobBuilder::new(query)
+                .literal_separator(true)
+                .build()
+                .map_err(invalid_query)?;
+
+            builder.add(glob);
+        }
+
+        builder.build().map(Matcher::Glob).map_err(invalid_query)
+    }
+
+    pub fn regex(queries: &[String]) -> io::Result<Matcher> {
+        RegexSet::new(queries)
+            .map(Matcher::Regex)
+            .map_err(invalid_query)
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Matcher::Contains(queries) => queries.iter().any(|query| text.contains(query.as_str())),
+            Matcher::Glob(set) => set.is_match(text),
+            Matcher::Regex(set) => set.is_match(text),
+        }
+    }
+
+    /// Patterns also match the path relative to the working directory, a substring only the name.
+    pub fn is_path_match(&self, relative_path: &Path) -> bool {
+        match self {
+            Matcher::Contains(_) => false,
+            Matcher::Glob(set) => set.is_match(relative_path),
+            Matcher::Regex(set) => set.is_match(&relative_path.to_string_lossy()),
+        }
+    }
+}
+
+fn invalid_query(error: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error)
+}
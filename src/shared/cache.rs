@@ -0,0 +1,165 @@
+use std::borrow::Cow;
+use std::env;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::paths;
+use crate::serialization::{Format, Groups};
+
+pub const REFRESH_VAR: &str = "JUSTLIST_CACHE_REFRESH";
+
+pub struct Cache {
+    path: PathBuf,
+}
+
+impl Cache {
+    // the key covers the plugin name, the directory it runs in and its arguments, so different
+    // queries get their own entry; plugins like search default to the current directory
+    pub fn for_current_args() -> Cache {
+        let mut hasher = Fnv1a::new();
+
+        if let Ok(dir) = env::current_dir() {
+            hasher.write(&os_bytes(dir.as_os_str()));
+        }
+
+        for arg in env::args_os().skip(1) {
+            hasher.write(&os_bytes(&arg));
+        }
+
+        let name = env::current_exe()
+            .ok()
+            .and_then(|x| x.file_stem().map(|x| x.to_string_lossy().to_string()))
+            .unwrap_or_else(|| String::from("plugin"));
+
+        let path = paths::cache_dir()
+            .join("plugins")
+            .join(format!("{}-{:016x}.json", name, hasher.finish()));
+
+        Cache { path }
+    }
+
+    pub fn read(&self) -> Option<(Groups, Duration)> {
+        let modified = fs::metadata(&self.path).ok()?.modified().ok()?;
+        let age = modified.elapsed().unwrap_or_default();
+
+        let reader = BufReader::new(File::open(&self.path).ok()?);
+        let groups = Groups::from_reader(reader, Some(Format::Json)).ok()?;
+
+        Some((groups, age))
+    }
+
+    /// Nothing is written for a result without items, it's more likely a failure than the answer.
+    pub fn write(&self, groups: &Groups) -> io::Result<()> {
+        if groups.groups.iter().all(|x| x.items.is_empty()) {
+            return Ok(());
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // write aside and rename, so a reader never sees a half written entry
+        let temp_path = self.path.with_extension(format!("{}.tmp", std::process::id()));
+        let writer = BufWriter::new(File::create(&temp_path)?);
+        serde_json::to_writer(writer, groups)?;
+
+        fs::rename(temp_path, &self.path)
+    }
+
+    pub fn refresh_in_background(&self) -> io::Result<()> {
+        Command::new(env::current_exe()?)
+            .args(env::args_os().skip(1))
+            .env(REFRESH_VAR, "1")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        Ok(())
+    }
+}
+
+// the exact bytes, so arguments that aren't valid UTF-8 don't collide once made lossy
+#[cfg(target_family = "unix")]
+fn os_bytes(s: &OsStr) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Cow::Borrowed(s.as_bytes())
+}
+
+#[cfg(target_family = "windows")]
+fn os_bytes(s: &OsStr) -> Cow<'_, [u8]> {
+    use std::os::windows::ffi::OsStrExt;
+
+    Cow::Owned(s.encode_wide().flat_map(u16::to_le_bytes).collect())
+}
+
+// the key names a file that outlives the build, so the hash has to stay the same across Rust
+// releases, which the standard hashers don't promise
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    fn new() -> Fnv1a {
+        Fnv1a(Fnv1a::OFFSET_BASIS)
+    }
+
+    // every part ends with a zero byte, so `ab c` and `a bc` don't collide
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes.iter().chain(&[0]) {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Fnv1a::PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_with_fnv1a() {
+        let mut hasher = Fnv1a::new();
+        hasher.write(b"a");
+
+        // FNV-1a of "a\0"
+        assert_eq!(hasher.finish(), 0x089b_e207_b544_f1e4);
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn hashes_the_exact_bytes_of_arguments() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut first = Fnv1a::new();
+        first.write(&os_bytes(OsStr::from_bytes(b"dir\xff")));
+
+        let mut second = Fnv1a::new();
+        second.write(&os_bytes(OsStr::from_bytes(b"dir\xfe")));
+
+        assert_ne!(first.finish(), second.finish());
+    }
+
+    #[test]
+    fn separates_the_parts() {
+        let mut first = Fnv1a::new();
+        first.write(b"ab");
+        first.write(b"c");
+
+        let mut second = Fnv1a::new();
+        second.write(b"a");
+        second.write(b"bc");
+
+        assert_ne!(first.finish(), second.finish());
+    }
+}
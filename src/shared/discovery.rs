@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::env::consts::EXE_SUFFIX;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::paths;
+use crate::plugin::{PluginDescription, DESCRIBE_FLAG};
+
+pub const PLUGIN_PREFIX: &str = "justlist-";
+const BUNDLED: [&str; 4] = ["search", "git-repo", "bookmarks", "bb-prs"];
+
+pub struct DiscoveredPlugin {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl DiscoveredPlugin {
+    pub fn describe(&self) -> Option<PluginDescription> {
+        let output = Command::new(&self.path)
+            .arg(DESCRIBE_FLAG)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        serde_json::from_slice(&output.stdout).ok()
+    }
+}
+
+// earlier locations win: bundled plugins, then the plugins directory, then PATH, where plugins
+// are named without their `justlist-` prefix
+pub fn discover() -> Vec<DiscoveredPlugin> {
+    let mut found: BTreeMap<String, PathBuf> = BTreeMap::new();
+
+    if let Some(dir) = env::current_exe()
+        .ok()
+        .and_then(|x| x.parent().map(Path::to_path_buf))
+    {
+        for name in BUNDLED.iter() {
+            let path = dir.join(format!("{}{}", name, EXE_SUFFIX));
+
+            if is_executable(&path) {
+                found.entry(name.to_string()).or_insert(path);
+            }
+        }
+    }
+
+    for (name, path) in list_executables(&paths::plugins_dir(), "") {
+        found.entry(name).or_insert(path);
+    }
+
+    if let Some(path_var) = env::var_os("PATH") {
+        for dir in env::split_paths(&path_var) {
+            for (name, path) in list_executables(&dir, PLUGIN_PREFIX) {
+                found.entry(name).or_insert(path);
+            }
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|(name, path)| DiscoveredPlugin { name, path })
+        .collect()
+}
+
+pub fn find(name: &str) -> Option<DiscoveredPlugin> {
+    discover().into_iter().find(|x| x.name == name)
+}
+
+/// The executable a command runs, looked up in the same order `discover` lists plugins, so a
+/// listed name can be passed to `--run` or used in a profile. A path, or a name that isn't a
+/// plugin, is left for the OS to find.
+pub fn locate(command: &str) -> PathBuf {
+    let is_bare_name = !command.contains('/') && !command.contains('\\');
+
+    if is_bare_name {
+        let file_name = format!("{}{}", command, EXE_SUFFIX);
+        let mut candidates = vec![];
+
+        if let Ok(mut sibling) = env::current_exe() {
+            sibling.pop();
+            candidates.push(sibling.join(&file_name));
+        }
+
+        candidates.push(paths::plugins_dir().join(&file_name));
+
+        if let Some(path_var) = env::var_os("PATH") {
+            for dir in env::split_paths(&path_var) {
+                candidates.push(dir.join(format!("{}{}", PLUGIN_PREFIX, file_name)));
+            }
+        }
+
+        if let Some(path) = candidates.into_iter().find(|x| is_executable(x)) {
+            return path;
+        }
+    }
+
+    PathBuf::from(command)
+}
+
+fn list_executables(dir: &Path, prefix: &str) -> Vec<(String, PathBuf)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut result = vec![];
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let name = file_name.strip_suffix(EXE_SUFFIX).unwrap_or(&file_name);
+
+        match name.strip_prefix(prefix) {
+            Some(name) if !name.is_empty() && is_executable(&path) => {
+                result.push((name.to_string(), path))
+            }
+            _ => {}
+        }
+    }
+
+    result
+}
+
+#[cfg(target_family = "unix")]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .map(|x| x.is_file() && x.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(target_family = "windows")]
+fn is_executable(path: &Path) -> bool {
+    path.is_file() && path.extension().map_or(false, |x| x == "exe")
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+    use crate::plugin::testing::TempDir;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn names_path_plugins_without_the_prefix() {
+        let dir = TempDir::builder()
+            .file("justlist-notes", "")
+            .file("justlist-", "")
+            .file("notes", "")
+            .file("justlist-readme", "")
+            .build();
+
+        for name in ["justlist-notes", "justlist-", "notes"] {
+            fs::set_permissions(dir.path().join(name), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let found = list_executables(dir.path(), PLUGIN_PREFIX);
+
+        assert_eq!(
+            found,
+            vec![("notes".to_string(), dir.path().join("justlist-notes"))]
+        );
+    }
+}
@@ -1,2 +1,7 @@
+pub mod cache;
+pub mod discovery;
+pub mod paths;
 pub mod plugin;
+pub mod runner;
 pub mod serialization;
+pub mod walk;
@@ -1,40 +1,393 @@
 use atty::Stream;
-use serde::Deserialize;
-use std::io::BufReader;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::{self, BufReader, Read, Write};
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
 
-use crate::serialization::Groups;
+use crate::cache::{Cache, REFRESH_VAR};
+use crate::serialization::{Groups, ListGroup};
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub const DESCRIBE_FLAG: &str = "--describe";
+
+/// Set by justlist for the plugins it runs itself. It reads their output while they run, so
+/// they may write their groups in several documents as they find them.
+pub const STREAM_VAR: &str = "JUSTLIST_STREAM";
+
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Serialize, Deserialize)]
+pub struct PluginDescription {
+    pub name: String,
+    pub description: Option<String>,
+    pub options: Vec<OptionDescription>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OptionDescription {
+    pub name: String,
+    pub short: Option<char>,
+    pub long: Option<String>,
+    pub help: Option<String>,
+    pub takes_value: bool,
+    pub required: bool,
+}
+
+impl PluginDescription {
+    pub fn new(name: &str, description: &str) -> PluginDescription {
+        PluginDescription {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            options: vec![],
+        }
+    }
+
+    /// A positional argument, always required.
+    pub fn argument(self, name: &str, help: &str) -> PluginDescription {
+        self.push(name, None, None, help, true, true)
+    }
+
+    /// An option taking a value, `--<long> <long>`.
+    pub fn option(self, long: &str, help: &str) -> PluginDescription {
+        self.push(long, None, Some(long), help, true, false)
+    }
+
+    /// An option taking a value that only has a short form, `-<short> <name>`.
+    pub fn short_option(self, short: char, name: &str, help: &str) -> PluginDescription {
+        self.push(name, Some(short), None, help, true, false)
+    }
+
+    /// A switch without a value.
+    pub fn flag(self, long: &str, help: &str) -> PluginDescription {
+        self.push(long, None, Some(long), help, false, false)
+    }
+
+    /// Adds a short form to the option or flag added last.
+    pub fn short(mut self, short: char) -> PluginDescription {
+        if let Some(option) = self.options.last_mut() {
+            option.short = Some(short);
+        }
+
+        self
+    }
+
+    fn push(
+        mut self,
+        name: &str,
+        short: Option<char>,
+        long: Option<&str>,
+        help: &str,
+        takes_value: bool,
+        required: bool,
+    ) -> PluginDescription {
+        self.options.push(OptionDescription {
+            name: name.to_string(),
+            short,
+            long: long.map(String::from),
+            help: Some(help.to_string()).filter(|x| !x.is_empty()),
+            takes_value,
+            required,
+        });
+
+        self
+    }
+}
+
+/// Options that can list what they accept, for `--describe`. They're written out next to the
+/// options, because clap 2 keeps its argument lists private.
+pub trait Describe {
+    fn describe() -> PluginDescription;
+}
+
+// answers --describe before the regular parsing, which would fail on missing required arguments
+pub fn parse_options<TOptions: StructOpt + Describe>() -> io::Result<TOptions> {
+    if env::args().nth(1).as_deref() == Some(DESCRIBE_FLAG) {
+        // a closed pipe, like `| head -1`, is reported rather than panicking in println!
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        serde_json::to_writer_pretty(&mut stdout, &TOptions::describe())?;
+        writeln!(stdout)?;
+
+        std::process::exit(0);
+    }
+
+    Ok(TOptions::from_args())
+}
 
 pub struct JustListPlugin<TOptions> {
     options: TOptions,
+    cache_ttl: Option<Duration>,
+    is_streamed: bool,
 }
 
+/// Produces the groups of a plugin. `groups` holds the groups read from stdin and the action
+/// adds its own. A plugin with a cache, see `JustListPlugin::cache_for`, gets an empty `groups`
+/// instead, so the cached result doesn't depend on the input; the groups from stdin are put in
+/// front of the produced ones afterwards.
 pub trait JustListAction<TOptions> {
     fn execute(&self, groups: &mut Groups, options: &TOptions);
+
+    /// Like `execute`, but may hand what was found so far to `batch` while it runs, so justlist
+    /// shows it early, see `Batches::send`. What was handed over is shown as is and mustn't be
+    /// left in `groups`, a group with the label of one handed over before adds its items to that
+    /// one. Called instead of `execute` when the output is streamed and not cached, by default
+    /// everything is written at the end.
+    fn execute_streamed(
+        &self,
+        groups: &mut Groups,
+        options: &TOptions,
+        batch: &mut dyn FnMut(Groups),
+    ) {
+        let _ = batch;
+        self.execute(groups, options);
+    }
+}
+
+/// Tells when to hand over the next batch: right away for the first one, so something shows
+/// up quickly, then at most every 100ms, so the stream isn't flooded with tiny documents.
+pub struct Batches {
+    last: Option<Instant>,
+}
+
+impl Batches {
+    pub fn new() -> Batches {
+        Batches { last: None }
+    }
+
+    pub fn is_due(&mut self) -> bool {
+        if self.last.is_some_and(|x| x.elapsed() < BATCH_INTERVAL) {
+            return false;
+        }
+
+        self.last = Some(Instant::now());
+        true
+    }
+
+    /// Hands `group` to `batch` together with what's left of `groups`, so the groups of the input
+    /// go out with the first batch and stay in front.
+    pub fn send(&self, groups: &mut Groups, group: ListGroup, batch: &mut dyn FnMut(Groups)) {
+        let mut document = Groups::new();
+        document.groups.append(&mut groups.groups);
+        document.groups.push(group);
+
+        batch(document);
+    }
+}
+
+impl Default for Batches {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<TOptions> JustListPlugin<TOptions> {
     pub fn new(options: TOptions) -> Self {
-        Self { options }
+        Self {
+            options,
+            cache_ttl: None,
+            is_streamed: env::var_os(STREAM_VAR).is_some(),
+        }
+    }
+
+    pub fn cache_for(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = if ttl.is_zero() { None } else { Some(ttl) };
+        self
+    }
+
+    /// Whether to write batches as they're found, see `STREAM_VAR`, which sets it by default.
+    pub fn streamed(mut self, is_streamed: bool) -> Self {
+        self.is_streamed = is_streamed;
+        self
     }
 
     pub fn main(&self, action: &impl JustListAction<TOptions>) -> std::io::Result<()> {
+        if env::var_os(REFRESH_VAR).is_some() {
+            let groups = self.execute(action);
+            return Cache::for_current_args().write(&groups);
+        }
+
         let stdin = std::io::stdin();
-        let handle = stdin.lock();
-        let reader = BufReader::new(handle);
+        let input = if atty::isnt(Stream::Stdin) {
+            Some(stdin.lock())
+        } else {
+            None
+        };
+
+        let stdout = std::io::stdout();
+        let writer = std::io::BufWriter::new(stdout.lock());
+
+        self.run(action, input, writer)
+    }
+
+    /// Reads the groups of the previous plugin from `input`, if there is one, and writes them
+    /// followed by the produced groups to `output`. A streamed run writes the batches of the
+    /// action first, one document per line.
+    pub fn run(
+        &self,
+        action: &impl JustListAction<TOptions>,
+        input: Option<impl Read>,
+        mut output: impl Write,
+    ) -> std::io::Result<()> {
+        let mut groups = match input {
+            Some(reader) => Groups::from_reader(BufReader::new(reader), None)?,
+            None => Groups::new(),
+        };
+
+        match self.cache_ttl {
+            Some(ttl) => {
+                let mut produced = self.execute_cached(action, ttl);
+                groups.groups.append(&mut produced.groups);
+            }
+            None if self.is_streamed => {
+                let mut result = Ok(());
+
+                action.execute_streamed(&mut groups, &self.options, &mut |batch| {
+                    if result.is_ok() {
+                        result = write_document(&mut output, &batch);
+                    }
+                });
 
-        let mut groups: Groups = Groups { groups: vec![] };
-        if atty::isnt(Stream::Stdin) {
-            let mut de = serde_json::Deserializer::from_reader(reader);
-            groups = Groups::deserialize(&mut de).unwrap();
+                result?;
+            }
+            None => action.execute(&mut groups, &self.options),
         }
 
+        write_document(&mut output, &groups)?;
+
+        Ok(())
+    }
+
+    fn execute(&self, action: &impl JustListAction<TOptions>) -> Groups {
+        let mut groups = Groups::new();
         action.execute(&mut groups, &self.options);
+        groups
+    }
 
-        let stdout = std::io::stdout();
-        let stdout_handle = stdout.lock();
-        let writer = std::io::BufWriter::new(stdout_handle);
-        serde_json::to_writer(writer, &groups)?;
+    // a stale entry is still served right away, the next run picks up the refreshed one
+    fn execute_cached(&self, action: &impl JustListAction<TOptions>, ttl: Duration) -> Groups {
+        let cache = Cache::for_current_args();
 
-        Ok(())
+        if let Some((groups, age)) = cache.read() {
+            if age > ttl {
+                if let Err(error) = cache.refresh_in_background() {
+                    eprintln!("can't refresh the cache: {}", error);
+                }
+            }
+
+            return groups;
+        }
+
+        let groups = self.execute(action);
+
+        if let Err(error) = cache.write(&groups) {
+            eprintln!("can't write the cache: {}", error);
+        }
+
+        groups
+    }
+}
+
+// flushed, the reader is waiting for it
+fn write_document(output: &mut impl Write, groups: &Groups) -> io::Result<()> {
+    serde_json::to_writer(&mut *output, groups)?;
+    writeln!(output)?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Nothing {}
+
+    impl JustListAction<()> for Nothing {
+        fn execute(&self, _groups: &mut Groups, _options: &()) {}
+    }
+
+    // replaces whatever came from upstream
+    struct Replace {}
+
+    impl JustListAction<()> for Replace {
+        fn execute(&self, groups: &mut Groups, _options: &()) {
+            groups.groups.clear();
+        }
+    }
+
+    // finds one item per batch
+    struct Countdown {}
+
+    impl JustListAction<()> for Countdown {
+        fn execute(&self, _groups: &mut Groups, _options: &()) {}
+
+        fn execute_streamed(
+            &self,
+            groups: &mut Groups,
+            _options: &(),
+            batch: &mut dyn FnMut(Groups),
+        ) {
+            let batches = Batches::new();
+
+            for label in ["2", "1"] {
+                let group = ListGroup {
+                    label: label.to_string(),
+                    items: vec![],
+                    command_template: None,
+                    is_terminal: None,
+                    actions: vec![],
+                };
+                batches.send(groups, group, batch);
+            }
+        }
+    }
+
+    #[test]
+    fn writes_an_empty_document_without_input() {
+        let mut output = vec![];
+        JustListPlugin::new(())
+            .run(&Nothing {}, None::<&[u8]>, &mut output)
+            .unwrap();
+
+        let groups = Groups::from_reader(output.as_slice(), None).unwrap();
+        assert!(groups.groups.is_empty());
+    }
+
+    #[test]
+    fn hands_the_input_to_an_action_without_a_cache() {
+        let input = br#"{"groups": [{"label": "upstream", "items": []}]}"#;
+        let mut output = vec![];
+        JustListPlugin::new(())
+            .run(&Replace {}, Some(&input[..]), &mut output)
+            .unwrap();
+
+        let groups = Groups::from_reader(output.as_slice(), None).unwrap();
+        assert!(groups.groups.is_empty());
+    }
+
+    #[test]
+    fn fails_on_an_input_that_isnt_a_document() {
+        let mut output = vec![];
+        let result = JustListPlugin::new(()).run(&Nothing {}, Some(&b"groups"[..]), &mut output);
+
+        assert!(result.is_err());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn writes_a_document_per_batch_when_streamed() {
+        let input = br#"{"groups": [{"label": "upstream", "items": []}]}"#;
+        let mut output = vec![];
+        JustListPlugin::new(())
+            .streamed(true)
+            .run(&Countdown {}, Some(&input[..]), &mut output)
+            .unwrap();
+
+        let labels: Vec<Vec<String>> = Groups::from_json_stream(output.as_slice())
+            .map(|x| x.unwrap().groups.into_iter().map(|x| x.label).collect())
+            .collect();
+
+        assert_eq!(labels, vec![vec!["upstream", "2"], vec!["1"], vec![]]);
     }
 }
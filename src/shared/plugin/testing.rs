@@ -0,0 +1,236 @@
+//! Helpers for testing plugins without spawning them: run a plugin against an in-memory
+//! input, read fixtures from `src/tests`, and build throwaway directory trees.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use structopt::StructOpt;
+
+use crate::plugin::{Describe, JustListAction, JustListPlugin};
+use crate::serialization::{Groups, ListGroup, SelectableItem};
+
+static TEMP_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs the plugin through `JustListPlugin::run`, like `main` does, with `input` passed as the
+/// JSON of stdin, and parses what would go to stdout.
+pub fn run<TOptions>(
+    plugin: &JustListPlugin<TOptions>,
+    action: &impl JustListAction<TOptions>,
+    input: Groups,
+) -> Groups {
+    let input = serde_json::to_vec(&input).unwrap();
+    let mut output = vec![];
+    plugin
+        .run(action, Some(input.as_slice()), &mut output)
+        .unwrap();
+
+    Groups::from_reader(output.as_slice(), None).unwrap()
+}
+
+/// Like `run`, for a plugin built with `.streamed(true)`, and returns every document it wrote.
+pub fn run_streamed<TOptions>(
+    plugin: &JustListPlugin<TOptions>,
+    action: &impl JustListAction<TOptions>,
+    input: Groups,
+) -> Vec<Groups> {
+    let input = serde_json::to_vec(&input).unwrap();
+    let mut output = vec![];
+    plugin
+        .run(action, Some(input.as_slice()), &mut output)
+        .unwrap();
+
+    Groups::from_json_stream(output.as_slice())
+        .map(Result::unwrap)
+        .collect()
+}
+
+/// Path of a file in `src/tests`.
+pub fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join("tests")
+        .join(name)
+}
+
+pub fn fixture(name: &str) -> String {
+    fs::read_to_string(fixture_path(name)).unwrap()
+}
+
+/// A document with a single group, as produced by an earlier plugin in a pipe.
+pub fn upstream_groups() -> Groups {
+    let mut groups = Groups::new();
+
+    groups.groups.push(ListGroup {
+        label: "upstream".to_string(),
+        items: vec![SelectableItem {
+            label: "item".to_string(),
+            param: "param".to_string(),
+            ..Default::default()
+        }],
+        command_template: Some("echo".to_string()),
+        is_terminal: Some(false),
+        actions: vec![],
+    });
+
+    groups
+}
+
+pub fn labels(group: &ListGroup) -> Vec<&str> {
+    let mut labels: Vec<&str> = group.items.iter().map(|x| x.label.as_str()).collect();
+    labels.sort_unstable();
+    labels
+}
+
+/// Checks that `describe()` lists the same arguments as the `--help` of the options, so the two
+/// can't drift apart. Compares names, short and long forms, whether a value is taken and the
+/// help text.
+pub fn assert_described<TOptions: StructOpt + Describe>() {
+    let mut help = vec![];
+    // a width of 0 turns off wrapping, every argument stays on one line
+    TOptions::clap()
+        .set_term_width(0)
+        .write_help(&mut help)
+        .unwrap();
+    let help = String::from_utf8(help).unwrap();
+
+    let mut expected = vec![];
+    let mut section = "";
+
+    for line in help.lines() {
+        let mut words = line.split_whitespace().peekable();
+
+        if !line.starts_with(' ') {
+            section = line;
+            continue;
+        }
+
+        if !matches!(section, "FLAGS:" | "OPTIONS:" | "ARGS:") {
+            continue;
+        }
+
+        let mut forms = vec![];
+
+        while let Some(form) = words.next_if(|x| x.starts_with('-')) {
+            forms.push(form.trim_end_matches(','));
+        }
+
+        if forms == ["-h", "--help"] || forms == ["-V", "--version"] {
+            continue;
+        }
+
+        // the value name of an option, or the name of a positional argument
+        let takes_value = section == "OPTIONS:" || section == "ARGS:";
+
+        if takes_value {
+            let value = words.next().unwrap();
+
+            if forms.is_empty() {
+                forms.push(value);
+            }
+        }
+
+        let text = words.collect::<Vec<_>>().join(" ");
+        let text = match text.find("[default:") {
+            Some(index) => text[..index].trim_end(),
+            None => &text,
+        };
+
+        expected.push(described(&forms.join(" "), takes_value, text));
+    }
+
+    let mut actual: Vec<String> = TOptions::describe()
+        .options
+        .iter()
+        .map(|x| {
+            let mut forms = vec![];
+            forms.extend(x.short.map(|x| format!("-{}", x)));
+            forms.extend(x.long.as_ref().map(|x| format!("--{}", x)));
+
+            if forms.is_empty() {
+                forms.push(format!("<{}>", x.name));
+            }
+
+            let help = x.help.as_deref().unwrap_or_default();
+            described(&forms.join(" "), x.takes_value, help)
+        })
+        .collect();
+
+    expected.sort_unstable();
+    actual.sort_unstable();
+    assert_eq!(actual, expected);
+}
+
+fn described(forms: &str, takes_value: bool, help: &str) -> String {
+    let value = if takes_value { " value" } else { "" };
+    format!("{}{}: {}", forms, value, help)
+}
+
+/// A directory under the system temp directory, removed on drop.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn builder() -> TempDirBuilder {
+        TempDirBuilder {
+            files: vec![],
+            dirs: vec![],
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+pub struct TempDirBuilder {
+    files: Vec<(PathBuf, String)>,
+    dirs: Vec<PathBuf>,
+}
+
+impl TempDirBuilder {
+    /// Adds a file, creating its parent directories.
+    pub fn file(mut self, path: &str, content: &str) -> Self {
+        self.files.push((PathBuf::from(path), content.to_string()));
+        self
+    }
+
+    /// Adds an empty directory, creating its parents.
+    pub fn dir(mut self, path: &str) -> Self {
+        self.dirs.push(PathBuf::from(path));
+        self
+    }
+
+    pub fn build(self) -> TempDir {
+        let counter = TEMP_DIR_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let name = format!("justlist-test-{}-{}", process::id(), counter);
+        let path = std::env::temp_dir().join(name);
+
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        for dir in self.dirs.iter() {
+            fs::create_dir_all(path.join(dir)).unwrap();
+        }
+
+        for (file, content) in self.files.iter() {
+            let file = path.join(file);
+
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent).unwrap();
+            }
+
+            fs::write(file, content).unwrap();
+        }
+
+        TempDir { path }
+    }
+}
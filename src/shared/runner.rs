@@ -0,0 +1,270 @@
+use serde::Deserialize;
+use std::io::{self, BufReader, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::discovery;
+use crate::plugin::STREAM_VAR;
+use crate::serialization::{Groups, ListGroup, SelectableItem};
+
+const WAIT_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PluginCommand {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Seconds to wait for the plugin before it is killed.
+    pub timeout: Option<u64>,
+}
+
+pub struct PluginResult {
+    pub source: usize,
+    pub command: String,
+    pub groups: io::Result<Groups>,
+}
+
+impl PluginResult {
+    pub fn into_groups(self) -> Groups {
+        match self.groups {
+            Ok(groups) => groups,
+            Err(error) => {
+                let message = error.to_string();
+                let mut groups = Groups::new();
+
+                let label = if error.kind() == io::ErrorKind::TimedOut {
+                    format!("{}: timeout", self.command)
+                } else {
+                    format!("{}: error", self.command)
+                };
+
+                groups.groups.push(ListGroup {
+                    label,
+                    // no param, there's nothing to launch
+                    items: vec![SelectableItem {
+                        label: message,
+                        ..Default::default()
+                    }],
+                    command_template: None,
+                    is_terminal: None,
+                    actions: vec![],
+                });
+
+                groups
+            }
+        }
+    }
+}
+
+impl PluginCommand {
+    /// Splits a command line like a POSIX shell would, so quoted arguments keep their spaces.
+    pub fn parse(command_line: &str) -> io::Result<PluginCommand> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+
+        let mut parts = shell_words::split(command_line)
+            .map_err(|e| invalid(format!("{}: {}", command_line, e)))?
+            .into_iter();
+
+        let command = parts
+            .next()
+            .ok_or_else(|| invalid(String::from("empty plugin command")))?;
+
+        Ok(PluginCommand {
+            command,
+            args: parts.collect(),
+            timeout: None,
+        })
+    }
+
+    /// Runs the plugin and hands each document it writes to `document` as soon as it's complete,
+    /// plugins built on `shared::plugin` write their groups in batches while they run.
+    pub fn run<F>(&self, default_timeout: Option<Duration>, document: F) -> io::Result<()>
+    where
+        F: FnMut(Groups) + Send + 'static,
+    {
+        let mut child = Command::new(discovery::locate(&self.command))
+            .args(&self.args)
+            .env(STREAM_VAR, "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // plugins read the groups of the previous one from stdin, give them an empty document
+        let mut stdin = child.stdin.take().unwrap();
+        serde_json::to_writer(&mut stdin, &Groups::new())?;
+        stdin.flush()?;
+        drop(stdin);
+
+        // drain the pipes while waiting, so a chatty plugin can't block on a full pipe
+        let stdout = read_documents_in_background(child.stdout.take().unwrap(), document);
+        let stderr = read_in_background(child.stderr.take().unwrap());
+
+        let timeout = self.timeout.map(Duration::from_secs).or(default_timeout);
+        // the error tab is labelled with the command already, the messages don't repeat it
+        let status = wait_with_timeout(&mut child, timeout)?;
+
+        let documents = stdout.join().unwrap_or(Ok(()));
+        let stderr = stderr.join().unwrap_or_default();
+
+        if !status.success() {
+            let stderr = String::from_utf8_lossy(&stderr);
+            let message = format!("{}, {}", status, stderr.trim());
+            return Err(io::Error::other(message));
+        }
+
+        documents
+    }
+}
+
+// stops at the first broken document, the ones before it are shown already
+fn read_documents_in_background<R, F>(reader: R, mut document: F) -> JoinHandle<io::Result<()>>
+where
+    R: Read + Send + 'static,
+    F: FnMut(Groups) + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut count = 0;
+
+        for groups in Groups::from_json_stream(BufReader::new(reader)) {
+            document(groups?);
+            count += 1;
+        }
+
+        if count == 0 {
+            let message = "no document in the output";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+
+        Ok(())
+    })
+}
+
+fn read_in_background<R>(mut reader: R) -> JoinHandle<Vec<u8>>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut buffer = vec![];
+        let _ = reader.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+fn wait_with_timeout(child: &mut Child, timeout: Option<Duration>) -> io::Result<ExitStatus> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return child.wait(),
+    };
+
+    let started = Instant::now();
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+
+        if started.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+
+            let message = format!("timed out after {}s", timeout.as_secs_f32());
+            return Err(io::Error::new(io::ErrorKind::TimedOut, message));
+        }
+
+        thread::sleep(WAIT_INTERVAL);
+    }
+}
+
+pub fn spawn_all(
+    commands: &[PluginCommand],
+    default_timeout: Option<Duration>,
+) -> Receiver<PluginResult> {
+    let (sender, receiver) = mpsc::channel();
+
+    for (source, command) in commands.iter().enumerate() {
+        let sender = sender.clone();
+        let command = command.clone();
+
+        thread::spawn(move || {
+            let name = command.command.clone();
+            let documents = sender.clone();
+
+            let result = command.run(default_timeout, move |groups| {
+                let _ = documents.send(PluginResult {
+                    source,
+                    command: name.clone(),
+                    groups: Ok(groups),
+                });
+            });
+
+            if let Err(error) = result {
+                let _ = sender.send(PluginResult {
+                    source,
+                    command: command.command,
+                    groups: Err(error),
+                });
+            }
+        });
+    }
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_command_lines_like_a_shell() {
+        let command = PluginCommand::parse("search 'My Docs' code -w \"a b\"").unwrap();
+
+        assert_eq!(command.command, "search");
+        assert_eq!(command.args, vec!["My Docs", "code", "-w", "a b"]);
+
+        assert!(PluginCommand::parse("search 'My Docs").is_err());
+        assert!(PluginCommand::parse("  ").is_err());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn names_the_command_once_in_a_timeout() {
+        let command = PluginCommand::parse("sleep 5").unwrap();
+        let result = PluginResult {
+            source: 0,
+            command: command.command.clone(),
+            groups: command
+                .run(Some(Duration::from_millis(100)), |_| {})
+                .map(|_| Groups::new()),
+        };
+
+        let groups = result.into_groups();
+
+        assert_eq!(groups.groups[0].label, "sleep: timeout");
+        assert_eq!(groups.groups[0].items[0].label, "timed out after 0.1s");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn sends_every_document_of_a_plugin() {
+        let script = r#"sh -c 'cat >/dev/null; echo "{\"groups\": [{\"label\": \"a\", \"items\": []}]}"; echo "{\"groups\": []}"'"#;
+        let commands = vec![PluginCommand::parse(script).unwrap()];
+
+        let results: Vec<PluginResult> = spawn_all(&commands, None).into_iter().collect();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].groups.as_ref().unwrap().groups[0].label, "a");
+        assert!(results[1].groups.as_ref().unwrap().groups.is_empty());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn fails_without_a_document() {
+        let command = PluginCommand::parse("sh -c 'cat >/dev/null'").unwrap();
+        let error = command.run(None, |_| {}).err().unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
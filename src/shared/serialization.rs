@@ -1,20 +1,360 @@
-use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize)]
+/// The version of the document produced by this build. Bumped on incompatible changes.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A single entry of a list.
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
 pub struct SelectableItem {
+    /// Text shown in the list and matched by the filter.
     pub label: String,
+    /// Value passed to the group's command template on launch, in place of `{}` or appended.
     pub param: String,
+    /// The exact bytes of the param, base64 encoded, when it is a path that isn't valid UTF-8.
+    /// `param` then holds a lossy copy, which is shown and copied, while this one is launched.
+    /// It's a separate field because `param` is a JSON string, which can't hold those bytes, and
+    /// changing its encoding would break existing plugins and consumers of the format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_param: Option<String>,
+    /// Secondary text rendered dimmed after the label.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// A glyph shown before the label, e.g. a Nerd Font icon or an emoji.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Short markers rendered after the description and matched by the filter.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Extra words matched by the filter but never shown.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Overrides the group's command template for this item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_template: Option<String>,
+    /// Overrides the group's terminal flag for this item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_terminal: Option<bool>,
+    /// Working directory of the launched command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Environment variables added to the launched command.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Values a command template refers to as `{name}`, e.g. `vim +{line} {}`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<String, String>,
+}
+
+impl SelectableItem {
+    pub fn for_path(label: String, path: &Path) -> SelectableItem {
+        SelectableItem {
+            label,
+            param: path.to_string_lossy().to_string(),
+            raw_param: raw_path(path),
+            ..Default::default()
+        }
+    }
+
+    /// The value to launch, the exact path if the item has one.
+    pub fn launch_param(&self) -> OsString {
+        self.raw_param
+            .as_deref()
+            .and_then(decode_raw)
+            .unwrap_or_else(|| OsString::from(&self.param))
+    }
+}
+
+#[cfg(target_family = "unix")]
+fn raw_path(path: &Path) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    match path.to_str() {
+        Some(_) => None,
+        None => Some(STANDARD.encode(path.as_os_str().as_bytes())),
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[cfg(target_family = "unix")]
+fn decode_raw(raw: &str) -> Option<OsString> {
+    use std::os::unix::ffi::OsStringExt;
+
+    STANDARD.decode(raw).ok().map(OsString::from_vec)
+}
+
+// paths on windows are UTF-16, unpaired surrogates are rare enough to go lossy
+#[cfg(target_family = "windows")]
+fn raw_path(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(target_family = "windows")]
+fn decode_raw(raw: &str) -> Option<OsString> {
+    let bytes = STANDARD.decode(raw).ok()?;
+    Some(OsString::from(String::from_utf8_lossy(&bytes).to_string()))
+}
+
+/// A list shown as a separate tab.
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct ListGroup {
+    /// Tab title.
     pub label: String,
     pub items: Vec<SelectableItem>,
+    /// Command the selected item's param is appended to.
     pub command_template: Option<String>,
+    /// Run the command in the current terminal instead of detaching it.
     pub is_terminal: Option<bool>,
+    /// Alternative commands offered in the action menu.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<Action>,
 }
 
-#[derive(Serialize, Deserialize)]
+/// A named command offered in the action menu, e.g. "open in terminal".
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Action {
+    pub name: String,
+    /// Command the selected item's param is appended to. The default opener is used if omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_template: Option<String>,
+    /// Run the command in the current terminal instead of detaching it.
+    pub is_terminal: Option<bool>,
+    /// Built-in behaviour used instead of a command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub builtin: Option<Builtin>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Builtin {
+    /// Copy the item's param to the clipboard.
+    Copy,
+    /// Copy the item's label to the clipboard.
+    CopyLabel,
+}
+
+/// The document passed between plugins and read by justlist.
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct Groups {
+    /// Protocol version the document was written for. Documents without it are treated as version 1.
+    #[serde(default = "default_version")]
+    pub version: u32,
     pub groups: Vec<ListGroup>,
 }
+
+fn default_version() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            _ => Err(format!("unknown format: {}, expected json, yaml or toml", s)),
+        }
+    }
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?;
+        Format::from_str(extension).ok()
+    }
+
+    pub fn detect(content: &str) -> Format {
+        let trimmed = content.trim_start();
+
+        if trimmed.starts_with('{') {
+            return Format::Json;
+        }
+
+        // a toml document is a valid yaml scalar at best, so try the stricter one first
+        if toml::from_str::<toml::Value>(content).is_ok() {
+            return Format::Toml;
+        }
+
+        Format::Yaml
+    }
+}
+
+impl Default for Groups {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Groups {
+    pub fn new() -> Groups {
+        Groups {
+            version: PROTOCOL_VERSION,
+            groups: vec![],
+        }
+    }
+
+    pub fn schema() -> RootSchema {
+        schema_for!(Groups)
+    }
+
+    pub fn from_reader<R>(mut reader: R, format: Option<Format>) -> io::Result<Groups>
+    where
+        R: Read,
+    {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+
+        Groups::parse(&content, format)
+    }
+
+    pub fn parse(content: &str, format: Option<Format>) -> io::Result<Groups> {
+        let format = format.unwrap_or_else(|| Format::detect(content));
+
+        let result = match format {
+            Format::Json => deserialize(&mut serde_json::Deserializer::from_str(content)),
+            Format::Yaml => deserialize(serde_yaml::Deserializer::from_str(content)),
+            Format::Toml => deserialize(toml::Deserializer::new(content)),
+        };
+
+        let groups = result.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Groups::check_version(groups)
+    }
+
+    /// Reads JSON documents written one after another, handing out each as soon as it's
+    /// complete. A plugin run by justlist may write its groups in several of them.
+    pub fn from_json_stream<R>(reader: R) -> impl Iterator<Item = io::Result<Groups>>
+    where
+        R: Read,
+    {
+        serde_json::Deserializer::from_reader(reader)
+            .into_iter::<serde_json::Value>()
+            .map(|value| {
+                let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+
+                let value = value.map_err(|e| invalid(e.to_string()))?;
+                let groups = deserialize(value).map_err(invalid)?;
+
+                Groups::check_version(groups)
+            })
+    }
+
+    fn check_version(groups: Groups) -> io::Result<Groups> {
+        if groups.version > PROTOCOL_VERSION {
+            let message = format!(
+                "version: unsupported protocol version {}, expected {} or lower",
+                groups.version, PROTOCOL_VERSION
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+
+        Ok(groups)
+    }
+}
+
+fn deserialize<'de, D>(de: D) -> Result<Groups, String>
+where
+    D: Deserializer<'de>,
+    D::Error: Display,
+{
+    serde_path_to_error::deserialize(de).map_err(|e| {
+        let path = e.path().to_string();
+        let message = e.inner().to_string();
+
+        // some formats already put the path into their messages
+        if message.starts_with(&path) {
+            message
+        } else {
+            format!("{}: {}", path, message)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{"groups": [{"label": "files", "items": [], "command_template": null, "is_terminal": null}]}"#;
+
+    const YAML: &str = "groups:\n  - label: files\n    items: []\n";
+
+    const TOML: &str = "[[groups]]\nlabel = \"files\"\nitems = []\n";
+
+    fn format_of(path: &str) -> Option<Format> {
+        Format::from_path(Path::new(path))
+    }
+
+    #[test]
+    fn reads_every_document_of_a_stream() {
+        let stream = format!("{}\n{}", JSON, JSON.replace("files", "more"));
+        let labels: Vec<String> = Groups::from_json_stream(stream.as_bytes())
+            .map(|x| x.unwrap().groups[0].label.clone())
+            .collect();
+
+        assert_eq!(labels, vec!["files", "more"]);
+    }
+
+    #[test]
+    fn reports_the_path_in_a_stream() {
+        let stream = r#"{"groups": [{"label": 1, "items": []}]}"#;
+        let error = Groups::from_json_stream(stream.as_bytes())
+            .next()
+            .unwrap()
+            .err()
+            .unwrap();
+
+        assert!(error.to_string().starts_with("groups[0].label: invalid type"));
+    }
+
+    #[test]
+    fn takes_the_format_from_the_extension() {
+        assert_eq!(format_of("list.json"), Some(Format::Json));
+        assert_eq!(format_of("list.YML"), Some(Format::Yaml));
+        assert_eq!(format_of("list.toml"), Some(Format::Toml));
+        assert_eq!(format_of("list.txt"), None);
+        assert_eq!(format_of("list"), None);
+    }
+
+    #[test]
+    fn detects_the_format_from_the_content() {
+        assert_eq!(Format::detect(JSON), Format::Json);
+        assert_eq!(Format::detect(&format!("\n  {}", JSON)), Format::Json);
+        assert_eq!(Format::detect(YAML), Format::Yaml);
+        assert_eq!(Format::detect(TOML), Format::Toml);
+    }
+
+    #[test]
+    fn parses_every_format() {
+        for content in [JSON, YAML, TOML] {
+            let groups = Groups::parse(content, None).unwrap();
+
+            assert_eq!(groups.version, 1);
+            assert_eq!(groups.groups[0].label, "files");
+        }
+    }
+
+    #[test]
+    fn an_explicit_format_overrides_detection() {
+        // valid as TOML and YAML alike, but only a YAML string scalar
+        let error = Groups::parse(TOML, Some(Format::Yaml)).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        assert!(Groups::parse(YAML, Some(Format::Yaml)).is_ok());
+        assert!("xml".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use ignore::{DirEntry, WalkParallel, WalkState};
+use std::sync::mpsc;
+use std::thread;
+
+/// Walks a tree on all cores and hands what `visit` returns for an entry to `found` on the
+/// calling thread, in no particular order, as soon as it's visited. A streamed plugin can pass
+/// it on in batches from there, see `plugin::Batches`.
+pub fn walk_parallel<T, V, F>(walker: WalkParallel, verbose: bool, visit: V, found: F)
+where
+    T: Send,
+    V: Fn(&DirEntry) -> Option<T> + Sync,
+    F: FnMut(T),
+{
+    walk_parallel_pruned(
+        walker,
+        verbose,
+        |entry| (visit(entry), WalkState::Continue),
+        found,
+    )
+}
+
+/// Like `walk_parallel`, but `visit` also tells whether to go on into a directory,
+/// `WalkState::Skip` leaves out everything below it.
+pub fn walk_parallel_pruned<T, V, F>(walker: WalkParallel, verbose: bool, visit: V, mut found: F)
+where
+    T: Send,
+    V: Fn(&DirEntry) -> (Option<T>, WalkState) + Sync,
+    F: FnMut(T),
+{
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        let visit = &visit;
+
+        scope.spawn(move || {
+            walker.run(|| {
+                let sender = sender.clone();
+
+                Box::new(move |item| match item {
+                    Ok(entry) => {
+                        let (result, state) = visit(&entry);
+
+                        if let Some(result) = result {
+                            let _ = sender.send(result);
+                        }
+
+                        state
+                    }
+                    Err(error) => {
+                        if verbose {
+                            eprintln!("{}", error);
+                        }
+
+                        WalkState::Continue
+                    }
+                })
+            })
+        });
+
+        for result in receiver {
+            found(result);
+        }
+    });
+}
@@ -1,3 +1,4 @@
+use crate::clipboard;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
@@ -30,6 +31,10 @@ impl TerminalState {
         self.output.push(String::from(output));
     }
 
+    pub fn copy_to_clipboard(&mut self, text: &str) {
+        self.output(&clipboard::osc52(text));
+    }
+
     fn print_output(&self) {
         for output in self.output.iter() {
             print!("{}", output);